@@ -0,0 +1,82 @@
+//! Compares matching a process list against patterns compiled on every call,
+//! as rule evaluation used to, with the precompiled `PatternSet`.
+//!
+//! Run with `cargo bench --bench patterns`.
+
+use process_cpu_auto::config::MatchMode;
+use process_cpu_auto::process::PatternSet;
+use regex::Regex;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use wildmatch::WildMatch;
+
+const ROUNDS: usize = 20;
+
+fn patterns() -> Vec<String> {
+    let mut patterns: Vec<String> = [
+        "exact:explorer.exe",
+        "exact:code.exe",
+        "*.game.exe",
+        "handbrake*.exe",
+        "ffmpeg*.exe",
+        "steam*.exe",
+        "obs??.exe",
+        "*render*",
+        "re:^backup(agent)?\\.exe$",
+        "re:^(msedge|chrome|firefox)\\.exe$",
+        "re:^java(w)?\\.exe$",
+        "re:^svc[a-z]+\\.exe$",
+        "re:(?i)^node(js)?\\.exe$",
+        "re:^python3?(\\.\\d+)?\\.exe$",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    patterns.extend((0..6).map(|i| format!("tool{}*.exe", i)));
+    patterns
+}
+
+fn process_names() -> Vec<String> {
+    let stems = ["svchost", "chrome", "worker", "my.game", "backupagent", "python3", "tool5x", "notepad"];
+    (0..300).map(|i| format!("{}{}.exe", stems[i % stems.len()], i / stems.len())).collect()
+}
+
+/// Match the way rule evaluation did before pattern sets: build each
+/// matcher again for every process
+fn naive_match(entry: &str, process_name: &str) -> bool {
+    if let Some(pattern) = entry.strip_prefix("exact:") {
+        pattern.to_lowercase() == process_name.to_lowercase()
+    } else if let Some(pattern) = entry.strip_prefix("re:") {
+        Regex::new(pattern).is_ok_and(|re| re.is_match(process_name))
+    } else {
+        WildMatch::new(&entry.to_lowercase()).matches(&process_name.to_lowercase())
+    }
+}
+
+fn time(label: &str, mut run: impl FnMut() -> usize) -> Duration {
+    let start = Instant::now();
+    let mut matched = 0;
+    for _ in 0..ROUNDS {
+        matched += black_box(run());
+    }
+    let elapsed = start.elapsed() / ROUNDS as u32;
+    println!("{:<12} {:>10.3?} per scan ({} matches)", label, elapsed, matched / ROUNDS);
+    elapsed
+}
+
+fn main() {
+    let patterns = patterns();
+    let names = process_names();
+    let set = PatternSet::parse(&patterns, &MatchMode::Wildcard).expect("benchmark patterns are valid");
+
+    println!("{} processes x {} patterns", names.len(), patterns.len());
+    let naive = time("per call", || {
+        names
+            .iter()
+            .filter(|name| patterns.iter().any(|pattern| naive_match(pattern, name)))
+            .count()
+    });
+    let compiled = time("pattern set", || names.iter().filter(|name| set.is_match(name)).count());
+
+    println!("speedup: {:.1}x", naive.as_secs_f64() / compiled.as_secs_f64());
+}
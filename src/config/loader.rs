@@ -52,6 +52,8 @@ impl ConfigLoader {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::MatchMode;
+    use crate::cpu::DetectionMode;
 
     #[test]
     fn test_default_config() {
@@ -59,4 +61,22 @@ mod tests {
         assert_eq!(config.service.scan_interval_ms, 1000);
         assert_eq!(config.service.log_level, "info");
     }
+
+    #[test]
+    fn test_unknown_modes_are_rejected() {
+        let error = toml::from_str::<Config>("[whitelist]\nmatch_mode = \"regx\"\n").unwrap_err().to_string();
+        assert!(error.contains("Unknown match_mode \"regx\"; expected exact, wildcard or regex"), "{}", error);
+
+        let error = toml::from_str::<Config>("[[rules]]\nmatch_mode = \"Exactly\"\n").unwrap_err().to_string();
+        assert!(error.contains("Unknown match_mode \"Exactly\""), "{}", error);
+
+        let error = toml::from_str::<Config>("[cpu]\ndetection_mode = \"automatic\"\n").unwrap_err().to_string();
+        assert!(error.contains("expected auto, manual or all_cores"), "{}", error);
+
+        let toml = "[cpu]\ndetection_mode = \"All_Cores\"\n[whitelist]\nmatch_mode = \"REGEX\"\n";
+        let config = toml::from_str::<Config>(toml).unwrap();
+        assert_eq!(config.cpu.detection_mode, DetectionMode::AllCores);
+        assert_eq!(config.whitelist.match_mode, MatchMode::Regex);
+        assert_eq!(toml::to_string(&config).unwrap().matches("\"all_cores\"").count(), 1);
+    }
 }
@@ -3,5 +3,5 @@ pub mod settings;
 pub mod watcher;
 
 pub use loader::ConfigLoader;
-pub use settings::{Config, ServiceConfig, CpuConfig, WhitelistConfig, MatchMode};
+pub use settings::{Config, ServiceConfig, CpuConfig, WhitelistConfig, RuleConfig, ScheduleConfig, IdleDemotionConfig, MatchMode};
 pub use watcher::ConfigWatcher;
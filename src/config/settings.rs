@@ -1,6 +1,10 @@
-use serde::{Deserialize, Serialize};
+use crate::cpu::{CoreTarget, CpuSet, DetectionMode};
+use crate::utils::ServiceError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Config {
     #[serde(default)]
     pub service: ServiceConfig,
@@ -10,6 +14,9 @@ pub struct Config {
     pub whitelist: WhitelistConfig,
     #[serde(default)]
     pub advanced: AdvancedConfig,
+    /// Rules checked in order before the whitelist
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RuleConfig>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -24,22 +31,148 @@ pub struct ServiceConfig {
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CpuConfig {
-    #[serde(default = "default_detection_mode")]
-    pub detection_mode: String,
     #[serde(default)]
-    pub p_cores: Vec<u32>,
+    pub detection_mode: DetectionMode,
+    #[serde(default)]
+    pub p_cores: CpuSet,
     #[serde(default)]
-    pub e_cores: Vec<u32>,
+    pub e_cores: CpuSet,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct WhitelistConfig {
-    #[serde(default = "default_match_mode")]
-    pub match_mode: String,
+    /// Matcher for entries without an `exact:`, `glob:` or `re:` prefix
+    #[serde(default)]
+    pub match_mode: MatchMode,
     #[serde(default)]
     pub processes: Vec<String>,
+    /// Never pinned by any rule; matched like `processes`
     #[serde(default)]
     pub exclude_processes: Vec<String>,
+    /// Match `exclude_processes` as case-insensitive substrings, as older
+    /// versions did
+    #[serde(default)]
+    pub substring_exclusions: bool,
+    /// Processors whitelisted processes are pinned to
+    #[serde(default)]
+    pub target: CoreTarget,
+    /// Used instead of `target` on AC power
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ac_target: Option<CoreTarget>,
+    /// Used instead of `target` on battery power
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub battery_target: Option<CoreTarget>,
+    /// Move the foreground process to `focus_target` while it has focus
+    #[serde(default)]
+    pub boost_on_focus: bool,
+    /// Where a boosted process goes; P-cores by default
+    #[serde(default)]
+    pub focus_target: CoreTarget,
+    /// Move matched processes that stay idle to another target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_demotion: Option<IdleDemotionConfig>,
+    /// Keep each process within the single NUMA node holding most of `target`
+    #[serde(default)]
+    pub single_numa_node: bool,
+}
+
+/// A `[[rules]]` entry: processes matching `processes` are pinned to `target`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RuleConfig {
+    /// Name used in logs; defaults to `rule<N>`
+    #[serde(default)]
+    pub name: String,
+    /// Higher priorities are checked first; equal priorities keep file order
+    #[serde(default)]
+    pub priority: i32,
+    /// Matcher for entries without an `exact:`, `glob:` or `re:` prefix
+    #[serde(default)]
+    pub match_mode: MatchMode,
+    #[serde(default)]
+    pub processes: Vec<String>,
+    /// Globs over the full executable path, e.g. `D:/Games/**/*.exe`; with
+    /// `processes` set, both must match
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Patterns over the full command line; checked only for processes that
+    /// passed `processes` and `paths`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cmdline: Vec<String>,
+    /// Patterns over the owning account (`DOMAIN\\user`, the bare user
+    /// name, or a UID on Linux)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<String>,
+    /// Login session IDs the process must run in
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sessions: Vec<u32>,
+    /// Processes matching these skip this rule and fall through to the next
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub target: CoreTarget,
+    /// Used instead of `target` on AC power
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ac_target: Option<CoreTarget>,
+    /// Used instead of `target` on battery power
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub battery_target: Option<CoreTarget>,
+    /// Move the foreground process to `focus_target` while it has focus
+    #[serde(default)]
+    pub boost_on_focus: bool,
+    /// Where a boosted process goes; P-cores by default
+    #[serde(default)]
+    pub focus_target: CoreTarget,
+    /// Move matched processes that stay idle to another target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_demotion: Option<IdleDemotionConfig>,
+    #[serde(default)]
+    pub single_numa_node: bool,
+    /// Also pin descendants of matching processes that match no rule themselves
+    #[serde(default)]
+    pub inherit_children: bool,
+    /// How many generations `inherit_children` reaches; unlimited if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<u32>,
+    /// Time windows the rule applies in; always applies if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedule: Vec<ScheduleConfig>,
+}
+
+/// A `[[rules.schedule]]` window, e.g. weekdays from 09:00 to 18:00
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ScheduleConfig {
+    /// Weekdays (`mon`, `tuesday`, ranges such as `mon-fri`); every day if empty
+    #[serde(default)]
+    pub days: Vec<String>,
+    /// `HH:MM`; a window whose end is not after its start runs past midnight
+    #[serde(default = "default_schedule_time")]
+    pub start: String,
+    /// `HH:MM`, or `24:00`; equal to `start` for the whole day
+    #[serde(default = "default_schedule_time")]
+    pub end: String,
+    /// `local`, `utc`, a fixed offset such as `+02:00`, or an IANA zone
+    /// such as `Europe/Berlin`
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+}
+
+/// A `[rules.idle_demotion]` table: processes whose CPU use stays below
+/// `demote_below_percent` over `window_secs` move to `target`, and move back
+/// once it rises above `promote_above_percent`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IdleDemotionConfig {
+    /// Percent of one logical processor, averaged over the window
+    #[serde(default = "default_demote_below")]
+    pub demote_below_percent: f64,
+    /// Must be above `demote_below_percent`, so processes near the threshold
+    /// do not move on every scan
+    #[serde(default = "default_promote_above")]
+    pub promote_above_percent: f64,
+    #[serde(default = "default_usage_window")]
+    pub window_secs: u64,
+    /// Where idle processes go; E-cores by default
+    #[serde(default = "default_idle_target")]
+    pub target: CoreTarget,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -54,23 +187,53 @@ pub struct AdvancedConfig {
     pub retry_delay_ms: u64,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub enum MatchMode {
     Exact,
+    #[default]
     Wildcard,
     Regex,
 }
 
-impl MatchMode {
-    pub fn from_str(s: &str) -> Self {
+impl FromStr for MatchMode {
+    type Err = ServiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
-            "exact" => MatchMode::Exact,
-            "regex" => MatchMode::Regex,
-            _ => MatchMode::Wildcard,
+            "exact" => Ok(MatchMode::Exact),
+            "wildcard" => Ok(MatchMode::Wildcard),
+            "regex" => Ok(MatchMode::Regex),
+            _ => Err(ServiceError::Config(format!(
+                "Unknown match_mode {:?}; expected exact, wildcard or regex",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for MatchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatchMode::Exact => write!(f, "exact"),
+            MatchMode::Wildcard => write!(f, "wildcard"),
+            MatchMode::Regex => write!(f, "regex"),
         }
     }
 }
 
+impl Serialize for MatchMode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MatchMode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 // Default values
 fn default_scan_interval() -> u64 {
     1000
@@ -84,12 +247,28 @@ fn default_log_file() -> String {
     "C:\\ProgramData\\ProcessCpuAuto\\service.log".to_string()
 }
 
-fn default_detection_mode() -> String {
-    "auto".to_string()
+fn default_schedule_time() -> String {
+    "00:00".to_string()
 }
 
-fn default_match_mode() -> String {
-    "wildcard".to_string()
+fn default_timezone() -> String {
+    "local".to_string()
+}
+
+fn default_demote_below() -> f64 {
+    1.0
+}
+
+fn default_promote_above() -> f64 {
+    5.0
+}
+
+fn default_usage_window() -> u64 {
+    30
+}
+
+fn default_idle_target() -> CoreTarget {
+    CoreTarget::ECores
 }
 
 fn default_cache_cleanup_interval() -> u64 {
@@ -117,9 +296,9 @@ impl Default for ServiceConfig {
 impl Default for CpuConfig {
     fn default() -> Self {
         Self {
-            detection_mode: default_detection_mode(),
-            p_cores: Vec::new(),
-            e_cores: Vec::new(),
+            detection_mode: DetectionMode::default(),
+            p_cores: CpuSet::new(),
+            e_cores: CpuSet::new(),
         }
     }
 }
@@ -127,12 +306,20 @@ impl Default for CpuConfig {
 impl Default for WhitelistConfig {
     fn default() -> Self {
         Self {
-            match_mode: default_match_mode(),
+            match_mode: MatchMode::default(),
             processes: Vec::new(),
             exclude_processes: vec![
-                "system".to_string(),
-                "svchost.exe".to_string(),
+                "exact:system".to_string(),
+                "exact:svchost.exe".to_string(),
             ],
+            substring_exclusions: false,
+            target: CoreTarget::default(),
+            ac_target: None,
+            battery_target: None,
+            boost_on_focus: false,
+            focus_target: CoreTarget::default(),
+            idle_demotion: None,
+            single_numa_node: false,
         }
     }
 }
@@ -147,14 +334,3 @@ impl Default for AdvancedConfig {
         }
     }
 }
-
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            service: ServiceConfig::default(),
-            cpu: CpuConfig::default(),
-            whitelist: WhitelistConfig::default(),
-            advanced: AdvancedConfig::default(),
-        }
-    }
-}
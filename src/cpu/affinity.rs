@@ -1,47 +1,68 @@
-use crate::cpu::types::CoreInfo;
+use crate::cpu::backend::{default_backend, AffinityBackend};
+use crate::cpu::cpuset::CpuSet;
+use crate::cpu::types::{CoreInfo, CoreTarget};
 use crate::utils::ServiceError;
 use std::sync::Arc;
-use windows::Win32::Foundation::{CloseHandle, HANDLE};
-use windows::Win32::System::Threading::{
-    OpenProcess, SetProcessAffinityMask, PROCESS_ACCESS_RIGHTS, PROCESS_SET_INFORMATION,
-    PROCESS_QUERY_INFORMATION,
-};
 
 pub struct AffinityManager {
     core_info: Arc<CoreInfo>,
+    backend: Box<dyn AffinityBackend>,
 }
 
 impl AffinityManager {
     pub fn new(core_info: Arc<CoreInfo>) -> Self {
-        Self { core_info }
+        Self::with_backend(core_info, default_backend())
     }
 
-    pub fn set_affinity_to_p_cores(&self, pid: u32, process_name: &str) -> Result<(), ServiceError> {
-        self.set_affinity(pid, self.core_info.p_core_mask, process_name)
+    /// Create an affinity manager that applies masks through the given backend
+    pub fn with_backend(core_info: Arc<CoreInfo>, backend: Box<dyn AffinityBackend>) -> Self {
+        Self { core_info, backend }
     }
 
-    pub fn set_affinity(&self, pid: u32, affinity_mask: usize, process_name: &str) -> Result<(), ServiceError> {
-        let handle = self.open_process(pid)?;
-
-        let result = unsafe {
-            SetProcessAffinityMask(handle, affinity_mask)
-        };
+    pub fn set_affinity_to_p_cores(&self, pid: u32, process_name: &str) -> Result<(), ServiceError> {
+        self.set_affinity(pid, &self.core_info.p_cores, process_name)
+    }
 
-        // Close handle
-        unsafe {
-            let _ = CloseHandle(handle);
+    /// Pin a process to the processors a target resolves to on this machine
+    ///
+    /// With `single_numa_node`, the processors are narrowed to one NUMA node;
+    /// see `CoreInfo::confine_to_numa_node`.
+    pub fn set_affinity_to_target(
+        &self,
+        pid: u32,
+        target: &CoreTarget,
+        single_numa_node: bool,
+        process_name: &str,
+    ) -> Result<(), ServiceError> {
+        let mut cpus = self.core_info.resolve(target);
+        if single_numa_node {
+            cpus = self.core_info.confine_to_numa_node(&cpus, pid);
         }
 
-        if !result.as_bool() {
+        if cpus.is_empty() {
             return Err(ServiceError::AffinitySetting(format!(
-                "Failed to set affinity mask 0x{:X} for process {} (PID: {})",
-                affinity_mask, process_name, pid
+                "Core target {} matches no processors on this machine",
+                target
             )));
         }
+        self.set_affinity(pid, &cpus, process_name)
+    }
+
+    pub fn set_affinity(&self, pid: u32, cpus: &CpuSet, process_name: &str) -> Result<(), ServiceError> {
+        self.backend.set_affinity(pid, cpus).map_err(|e| {
+            let message = format!(
+                "Failed to set CPU affinity {} for process {} (PID: {}): {}",
+                cpus, process_name, pid, e
+            );
+            match e {
+                ServiceError::TransientAffinity(_) => ServiceError::TransientAffinity(message),
+                _ => ServiceError::AffinitySetting(message),
+            }
+        })?;
 
         log::debug!(
-            "Set CPU affinity mask 0x{:X} for process {} (PID: {})",
-            affinity_mask,
+            "Set CPU affinity {} for process {} (PID: {})",
+            cpus,
             process_name,
             pid
         );
@@ -49,26 +70,12 @@ impl AffinityManager {
         Ok(())
     }
 
-    fn open_process(&self, pid: u32) -> Result<HANDLE, ServiceError> {
-        let access_rights = PROCESS_ACCESS_RIGHTS(
-            PROCESS_SET_INFORMATION.0 | PROCESS_QUERY_INFORMATION.0
-        );
-
-        let handle = unsafe {
-            OpenProcess(access_rights, false, pid)
-        };
-
-        match handle {
-            Ok(h) if !h.is_invalid() => Ok(h),
-            _ => Err(ServiceError::AffinitySetting(format!(
-                "Failed to open process with PID: {} (insufficient permissions or process doesn't exist)",
-                pid
-            ))),
-        }
+    pub fn get_affinity(&self, pid: u32) -> Result<CpuSet, ServiceError> {
+        self.backend.get_affinity(pid)
     }
 
-    pub fn get_p_core_mask(&self) -> usize {
-        self.core_info.p_core_mask
+    pub fn get_p_cores(&self) -> &CpuSet {
+        &self.core_info.p_cores
     }
 
     pub fn get_core_info(&self) -> Arc<CoreInfo> {
@@ -79,14 +86,52 @@ impl AffinityManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingBackend {
+        calls: Mutex<Vec<(u32, CpuSet)>>,
+    }
+
+    impl AffinityBackend for Arc<RecordingBackend> {
+        fn get_affinity(&self, _pid: u32) -> Result<CpuSet, ServiceError> {
+            Ok(CpuSet::new())
+        }
+
+        fn set_affinity(&self, pid: u32, cpus: &CpuSet) -> Result<(), ServiceError> {
+            self.calls.lock().unwrap().push((pid, cpus.clone()));
+            Ok(())
+        }
+    }
 
     #[test]
     fn test_affinity_manager_creation() {
-        let p_cores = vec![0, 1, 2, 3];
-        let e_cores = vec![4, 5, 6, 7];
+        let p_cores = CpuSet::from_cpus(0..4);
+        let e_cores = CpuSet::from_cpus(4..8);
         let core_info = Arc::new(CoreInfo::new(p_cores, e_cores));
         let manager = AffinityManager::new(core_info);
 
-        assert_eq!(manager.get_p_core_mask(), 0x0F);
+        assert_eq!(manager.get_p_cores().group_mask(0), 0x0F);
+    }
+
+    #[test]
+    fn test_set_affinity_to_p_cores_uses_backend() {
+        let backend = Arc::new(RecordingBackend::default());
+        let core_info = Arc::new(CoreInfo::new(CpuSet::from_cpus(0..2), CpuSet::from_cpus(2..4)));
+        let manager = AffinityManager::with_backend(core_info, Box::new(Arc::clone(&backend)));
+
+        manager.set_affinity_to_p_cores(42, "game.exe").unwrap();
+        assert_eq!(*backend.calls.lock().unwrap(), vec![(42, CpuSet::from_cpus([0, 1]))]);
+    }
+
+    #[test]
+    fn test_set_affinity_to_target() {
+        let backend = Arc::new(RecordingBackend::default());
+        let core_info = Arc::new(CoreInfo::new(CpuSet::from_cpus(0..2), CpuSet::from_cpus(2..4)));
+        let manager = AffinityManager::with_backend(core_info, Box::new(Arc::clone(&backend)));
+
+        manager.set_affinity_to_target(42, &CoreTarget::ECores, false, "worker.exe").unwrap();
+        assert!(manager.set_affinity_to_target(42, &CoreTarget::Tier(5), false, "worker.exe").is_err());
+        assert_eq!(*backend.calls.lock().unwrap(), vec![(42, CpuSet::from_cpus([2, 3]))]);
     }
 }
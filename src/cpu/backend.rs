@@ -0,0 +1,81 @@
+use crate::cpu::cpuset::CpuSet;
+use crate::utils::ServiceError;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// A platform mechanism for reading and changing process CPU affinity
+pub trait AffinityBackend: Send + Sync {
+    /// Get the CPUs a process is currently allowed to run on
+    fn get_affinity(&self, pid: u32) -> Result<CpuSet, ServiceError>;
+
+    /// Restrict a process to the given CPUs
+    fn set_affinity(&self, pid: u32, cpus: &CpuSet) -> Result<(), ServiceError>;
+}
+
+/// Get the affinity backend for the current platform
+pub fn default_backend() -> Box<dyn AffinityBackend> {
+    #[cfg(windows)]
+    {
+        Box::new(crate::cpu::WindowsAffinityBackend)
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        Box::new(crate::cpu::LinuxAffinityBackend::new())
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        Box::new(UnsupportedBackend)
+    }
+}
+
+/// Backend that logs affinity changes instead of applying them
+///
+/// Used when replaying traces, where the PIDs do not refer to processes on
+/// this machine.
+#[derive(Default)]
+pub struct DryRunBackend {
+    affinities: Mutex<HashMap<u32, CpuSet>>,
+}
+
+impl DryRunBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AffinityBackend for DryRunBackend {
+    fn get_affinity(&self, pid: u32) -> Result<CpuSet, ServiceError> {
+        self.affinities.lock().unwrap().get(&pid).cloned().ok_or_else(|| {
+            ServiceError::AffinitySetting(format!("[dry-run] Affinity of PID: {} is unknown", pid))
+        })
+    }
+
+    fn set_affinity(&self, pid: u32, cpus: &CpuSet) -> Result<(), ServiceError> {
+        log::info!("[dry-run] Would set CPU affinity {} for PID: {}", cpus, pid);
+        self.affinities.lock().unwrap().insert(pid, cpus.clone());
+        Ok(())
+    }
+}
+
+/// Fallback for platforms without an affinity implementation
+#[cfg(not(any(windows, target_os = "linux")))]
+struct UnsupportedBackend;
+
+#[cfg(not(any(windows, target_os = "linux")))]
+impl AffinityBackend for UnsupportedBackend {
+    fn get_affinity(&self, pid: u32) -> Result<CpuSet, ServiceError> {
+        Err(ServiceError::AffinitySetting(format!(
+            "Reading affinity of PID {} is not supported on this platform",
+            pid
+        )))
+    }
+
+    fn set_affinity(&self, pid: u32, _cpus: &CpuSet) -> Result<(), ServiceError> {
+        Err(ServiceError::AffinitySetting(format!(
+            "Setting affinity of PID {} is not supported on this platform",
+            pid
+        )))
+    }
+}
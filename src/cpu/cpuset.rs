@@ -0,0 +1,513 @@
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::ops::{BitAnd, BitOr, Sub};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Number of logical processors in a Windows processor group
+pub const GROUP_SIZE: u32 = 64;
+
+/// A set of logical processors of any size
+///
+/// Parsed from and displayed as a `taskset`/cpuset-style list such as
+/// `0-7,16-23`; a token may also be a hex mask such as `0xFF00`.
+///
+/// CPUs are numbered `group * 64 + index`, where `group` is the Windows
+/// processor group and `index` the bit within that group's affinity mask.
+/// On Linux, and on Windows machines with at most 64 logical processors,
+/// this is the ordinary CPU number.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CpuSet {
+    /// One 64-bit mask per processor group; never has trailing empty groups
+    groups: Vec<u64>,
+}
+
+impl CpuSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_cpus<I: IntoIterator<Item = u32>>(cpus: I) -> Self {
+        let mut set = Self::new();
+        for cpu in cpus {
+            set.insert(cpu);
+        }
+        set
+    }
+
+    /// Build a set from one processor group's affinity mask
+    pub fn from_group_mask(group: u16, mask: u64) -> Self {
+        let mut set = Self::new();
+        set.set_group_mask(group, mask);
+        set
+    }
+
+    fn split(cpu: u32) -> (usize, u64) {
+        ((cpu / GROUP_SIZE) as usize, 1u64 << (cpu % GROUP_SIZE))
+    }
+
+    fn trim(&mut self) {
+        while self.groups.last() == Some(&0) {
+            self.groups.pop();
+        }
+    }
+
+    /// Add a CPU, returning `true` if it was not already present
+    pub fn insert(&mut self, cpu: u32) -> bool {
+        let (group, bit) = Self::split(cpu);
+        if self.groups.len() <= group {
+            self.groups.resize(group + 1, 0);
+        }
+        let added = self.groups[group] & bit == 0;
+        self.groups[group] |= bit;
+        added
+    }
+
+    /// Remove a CPU, returning `true` if it was present
+    pub fn remove(&mut self, cpu: u32) -> bool {
+        let (group, bit) = Self::split(cpu);
+        match self.groups.get_mut(group) {
+            Some(mask) if *mask & bit != 0 => {
+                *mask &= !bit;
+                self.trim();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn contains(&self, cpu: u32) -> bool {
+        let (group, bit) = Self::split(cpu);
+        self.groups.get(group).is_some_and(|mask| mask & bit != 0)
+    }
+
+    /// Number of CPUs in the set
+    pub fn len(&self) -> usize {
+        self.groups.iter().map(|mask| mask.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Lowest CPU in the set
+    pub fn first(&self) -> Option<u32> {
+        self.iter().next()
+    }
+
+    /// Iterate over the CPUs in ascending order
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            groups: &self.groups,
+            group: 0,
+            remaining: self.groups.first().copied().unwrap_or(0),
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<u32> {
+        self.iter().collect()
+    }
+
+    pub fn union(&self, other: &CpuSet) -> CpuSet {
+        let len = self.groups.len().max(other.groups.len());
+        let groups = (0..len)
+            .map(|g| self.groups.get(g).copied().unwrap_or(0) | other.groups.get(g).copied().unwrap_or(0))
+            .collect();
+        CpuSet { groups }
+    }
+
+    pub fn intersection(&self, other: &CpuSet) -> CpuSet {
+        let mut set = CpuSet {
+            groups: self.groups.iter().zip(&other.groups).map(|(a, b)| a & b).collect(),
+        };
+        set.trim();
+        set
+    }
+
+    pub fn difference(&self, other: &CpuSet) -> CpuSet {
+        let mut set = CpuSet {
+            groups: self
+                .groups
+                .iter()
+                .enumerate()
+                .map(|(g, mask)| mask & !other.groups.get(g).copied().unwrap_or(0))
+                .collect(),
+        };
+        set.trim();
+        set
+    }
+
+    pub fn is_subset(&self, other: &CpuSet) -> bool {
+        self.difference(other).is_empty()
+    }
+
+    /// Affinity mask of one processor group (0 if the set has no CPUs there)
+    pub fn group_mask(&self, group: u16) -> u64 {
+        self.groups.get(group as usize).copied().unwrap_or(0)
+    }
+
+    /// Replace the CPUs of one processor group
+    pub fn set_group_mask(&mut self, group: u16, mask: u64) {
+        let group = group as usize;
+        if self.groups.len() <= group {
+            self.groups.resize(group + 1, 0);
+        }
+        self.groups[group] = mask;
+        self.trim();
+    }
+
+    /// Non-empty processor groups with their affinity masks
+    pub fn group_masks(&self) -> impl Iterator<Item = (u16, u64)> + '_ {
+        self.groups
+            .iter()
+            .enumerate()
+            .filter(|(_, &mask)| mask != 0)
+            .map(|(group, &mask)| (group as u16, mask))
+    }
+
+    /// Number of processor groups the set spans
+    pub fn group_count(&self) -> usize {
+        self.group_masks().count()
+    }
+}
+
+/// Iterator over the CPUs of a `CpuSet`
+pub struct Iter<'a> {
+    groups: &'a [u64],
+    group: usize,
+    remaining: u64,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        while self.remaining == 0 {
+            self.group += 1;
+            self.remaining = *self.groups.get(self.group)?;
+        }
+
+        let bit = self.remaining.trailing_zeros();
+        self.remaining &= self.remaining - 1;
+        Some(self.group as u32 * GROUP_SIZE + bit)
+    }
+}
+
+impl<'a> IntoIterator for &'a CpuSet {
+    type Item = u32;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl FromIterator<u32> for CpuSet {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+        Self::from_cpus(iter)
+    }
+}
+
+impl BitOr for &CpuSet {
+    type Output = CpuSet;
+
+    fn bitor(self, other: &CpuSet) -> CpuSet {
+        self.union(other)
+    }
+}
+
+impl BitAnd for &CpuSet {
+    type Output = CpuSet;
+
+    fn bitand(self, other: &CpuSet) -> CpuSet {
+        self.intersection(other)
+    }
+}
+
+impl Sub for &CpuSet {
+    type Output = CpuSet;
+
+    fn sub(self, other: &CpuSet) -> CpuSet {
+        self.difference(other)
+    }
+}
+
+/// Formats as a CPU list, e.g. `0-7,16-23`
+impl fmt::Display for CpuSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut cpus = self.iter().peekable();
+        let mut first = true;
+
+        while let Some(start) = cpus.next() {
+            let mut end = start;
+            while cpus.peek() == Some(&(end + 1)) {
+                end = cpus.next().unwrap_or(end);
+            }
+
+            if !first {
+                write!(f, ",")?;
+            }
+            first = false;
+
+            if start == end {
+                write!(f, "{}", start)?;
+            } else {
+                write!(f, "{}-{}", start, end)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Error for a CPU list that could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid CPU list {input:?}: {reason} at {token:?} (column {column})")]
+pub struct CpuSetParseError {
+    /// The full list being parsed
+    pub input: String,
+    /// The offending token
+    pub token: String,
+    /// 1-based column of the token within the input
+    pub column: usize,
+    pub reason: String,
+}
+
+impl FromStr for CpuSet {
+    type Err = CpuSetParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut set = CpuSet::new();
+        let mut offset = 0;
+
+        for raw in input.split(',') {
+            let token = raw.trim();
+            let column = offset + (raw.len() - raw.trim_start().len()) + 1;
+            offset += raw.len() + 1;
+
+            let error = |reason: &str| CpuSetParseError {
+                input: input.to_string(),
+                token: token.to_string(),
+                column,
+                reason: reason.to_string(),
+            };
+
+            if token.is_empty() {
+                // Allow "" for an empty set and a trailing comma
+                if input.trim().is_empty() || offset > input.len() {
+                    continue;
+                }
+                return Err(error("empty entry"));
+            }
+
+            if let Some(hex) = token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")) {
+                set = set.union(&parse_hex_mask(hex).map_err(error)?);
+                continue;
+            }
+
+            match token.split_once('-') {
+                Some((start, end)) => {
+                    let start = parse_cpu(start.trim()).map_err(error)?;
+                    let end = parse_cpu(end.trim()).map_err(error)?;
+                    if start > end {
+                        return Err(error("range start is greater than its end"));
+                    }
+                    for cpu in start..=end {
+                        set.insert(cpu);
+                    }
+                }
+                None => {
+                    set.insert(parse_cpu(token).map_err(error)?);
+                }
+            }
+        }
+
+        Ok(set)
+    }
+}
+
+/// Upper bound on CPU numbers, to keep typos like `0-4000000000` from
+/// allocating gigabytes
+const MAX_CPU: u32 = 1 << 16;
+
+fn parse_cpu(text: &str) -> Result<u32, &'static str> {
+    let cpu: u32 = text.parse().map_err(|_| "expected a CPU number")?;
+    if cpu >= MAX_CPU {
+        return Err("CPU number is out of range");
+    }
+    Ok(cpu)
+}
+
+fn parse_hex_mask(hex: &str) -> Result<CpuSet, &'static str> {
+    let digits: Vec<u32> = hex
+        .chars()
+        .filter(|&c| c != '_')
+        .map(|c| c.to_digit(16).ok_or("expected hex digits after 0x"))
+        .collect::<Result<_, _>>()?;
+
+    if digits.is_empty() {
+        return Err("expected hex digits after 0x");
+    }
+    if digits.len() as u32 * 4 > MAX_CPU {
+        return Err("hex mask is too long");
+    }
+
+    // The last digit holds CPUs 0-3
+    let mut set = CpuSet::new();
+    for (position, digit) in digits.iter().rev().enumerate() {
+        for bit in 0..4 {
+            if digit & (1 << bit) != 0 {
+                set.insert(position as u32 * 4 + bit);
+            }
+        }
+    }
+    Ok(set)
+}
+
+impl Serialize for CpuSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Accepts a CPU list string (`"0-7,16-23"`, `"0xFF00"`) or an array of CPU numbers
+impl<'de> Deserialize<'de> for CpuSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CpuSetVisitor;
+
+        impl<'de> Visitor<'de> for CpuSetVisitor {
+            type Value = CpuSet;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a CPU list such as \"0-7,16-23\" or an array of CPU numbers")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<CpuSet, E> {
+                value.parse().map_err(E::custom)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<CpuSet, A::Error> {
+                let mut set = CpuSet::new();
+                while let Some(cpu) = seq.next_element::<u32>()? {
+                    if cpu >= MAX_CPU {
+                        return Err(de::Error::custom(format!("CPU number {} is out of range", cpu)));
+                    }
+                    set.insert(cpu);
+                }
+                Ok(set)
+            }
+        }
+
+        deserializer.deserialize_any(CpuSetVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_iterate_across_groups() {
+        let set = CpuSet::from_cpus([130, 0, 63, 64, 5]);
+        assert_eq!(set.to_vec(), vec![0, 5, 63, 64, 130]);
+        assert_eq!(set.len(), 5);
+        assert_eq!(set.group_count(), 3);
+        assert_eq!(set.group_mask(0), (1 << 63) | (1 << 5) | 1);
+        assert_eq!(set.group_mask(1), 1);
+        assert_eq!(set.group_mask(2), 1 << 2);
+        assert_eq!(set.group_mask(7), 0);
+        assert!(set.contains(130));
+        assert!(!set.contains(129));
+    }
+
+    #[test]
+    fn test_set_algebra() {
+        let a = CpuSet::from_cpus(0..8);
+        let b = CpuSet::from_cpus(4..70);
+
+        assert_eq!((&a | &b).len(), 70);
+        assert_eq!((&a & &b).to_vec(), vec![4, 5, 6, 7]);
+        assert_eq!((&a - &b).to_vec(), vec![0, 1, 2, 3]);
+        assert_eq!((&b - &a).first(), Some(8));
+        assert!((&a & &b).is_subset(&a));
+        assert!(!a.is_subset(&b));
+    }
+
+    #[test]
+    fn test_remove_trims_empty_groups() {
+        let mut set = CpuSet::from_cpus([1, 100]);
+        assert!(set.remove(100));
+        assert!(!set.remove(100));
+        assert_eq!(set, CpuSet::from_cpus([1]));
+        assert!(set.remove(1));
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(CpuSet::from_cpus([0, 1, 2, 3, 5, 8, 9, 127, 128]).to_string(), "0-3,5,8-9,127-128");
+        assert_eq!(CpuSet::new().to_string(), "");
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let set: CpuSet = "0-7,16-23".parse().unwrap();
+        assert_eq!(set.len(), 16);
+        assert!(set.contains(16) && !set.contains(8));
+
+        assert_eq!("5".parse::<CpuSet>().unwrap().to_vec(), vec![5]);
+        assert_eq!(" 0 - 2 , 4 ,".parse::<CpuSet>().unwrap().to_vec(), vec![0, 1, 2, 4]);
+        assert!("".parse::<CpuSet>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_hex_mask() {
+        assert_eq!("0xFF00".parse::<CpuSet>().unwrap(), CpuSet::from_cpus(8..16));
+        assert_eq!("0x1,0x10".parse::<CpuSet>().unwrap().to_vec(), vec![0, 4]);
+
+        // Masks wider than one processor group
+        let wide: CpuSet = "0x1_0000000000000001".parse().unwrap();
+        assert_eq!(wide.to_vec(), vec![0, 64]);
+    }
+
+    #[test]
+    fn test_parse_errors_point_at_token() {
+        let error = "0-7,1x,16-23".parse::<CpuSet>().unwrap_err();
+        assert_eq!(error.token, "1x");
+        assert_eq!(error.column, 5);
+        assert!(error.to_string().contains("\"1x\""));
+
+        let error = "0-3, 9-4".parse::<CpuSet>().unwrap_err();
+        assert_eq!(error.token, "9-4");
+        assert_eq!(error.column, 6);
+
+        assert_eq!("0,,1".parse::<CpuSet>().unwrap_err().reason, "empty entry");
+        assert!("0xZZ".parse::<CpuSet>().is_err());
+        assert!("0-100000".parse::<CpuSet>().is_err());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let set = CpuSet::from_cpus([0, 1, 2, 3, 16, 17, 100]);
+        assert_eq!(set.to_string().parse::<CpuSet>().unwrap(), set);
+    }
+
+    #[test]
+    fn test_serde() {
+        #[derive(Serialize, Deserialize)]
+        struct Wrapper {
+            cores: CpuSet,
+        }
+
+        let parsed: Wrapper = toml::from_str("cores = [3, 1, 70]").unwrap();
+        assert_eq!(parsed.cores.to_vec(), vec![1, 3, 70]);
+
+        let parsed: Wrapper = toml::from_str("cores = \"0-3,8\"").unwrap();
+        assert_eq!(parsed.cores.to_vec(), vec![0, 1, 2, 3, 8]);
+        assert_eq!(toml::to_string(&parsed).unwrap().trim(), "cores = \"0-3,8\"");
+
+        let error = toml::from_str::<Wrapper>("cores = \"0-3,abc\"").err().unwrap();
+        assert!(error.to_string().contains("\"abc\""));
+    }
+}
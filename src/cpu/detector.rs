@@ -1,19 +1,106 @@
-use crate::cpu::types::{CoreInfo, DetectionMode};
+use crate::cpu::cpuset::CpuSet;
+#[cfg(windows)]
+use crate::cpu::cpuset::GROUP_SIZE;
+#[cfg(target_os = "linux")]
+use crate::cpu::sysfs::SysfsDetector;
+#[cfg(windows)]
+use crate::cpu::topology;
+use crate::cpu::types::{CacheGroup, CoreInfo, DetectionMode, NumaNode};
 use crate::utils::ServiceError;
 use std::sync::Arc;
 
 pub struct CpuDetector;
 
 impl CpuDetector {
-    pub fn detect(mode: DetectionMode, p_cores: Vec<u32>, e_cores: Vec<u32>) -> Result<Arc<CoreInfo>, ServiceError> {
-        match mode {
-            DetectionMode::Auto => Self::detect_auto(),
-            DetectionMode::Manual => Self::detect_manual(p_cores, e_cores),
-            DetectionMode::AllCores => Self::detect_all_cores(),
+    pub fn detect(mode: DetectionMode, p_cores: CpuSet, e_cores: CpuSet) -> Result<Arc<CoreInfo>, ServiceError> {
+        let info = match mode {
+            DetectionMode::Auto => Self::detect_auto()?,
+            DetectionMode::Manual => Self::detect_manual(p_cores, e_cores)?,
+            DetectionMode::AllCores => Self::detect_all_cores()?,
+        };
+
+        let info = Self::with_smt_topology(info);
+        let info = Self::with_cache_topology(info);
+        Ok(Arc::new(Self::with_numa_topology(info)))
+    }
+
+    /// Add the SMT sibling groups, used for the `*_physical` targets
+    fn with_smt_topology(info: CoreInfo) -> CoreInfo {
+        match Self::detect_physical_cores() {
+            Ok(cores) if !cores.is_empty() => {
+                log::info!("Detected {} physical core(s)", cores.len());
+                info.with_physical_cores(cores)
+            }
+            Ok(_) => info,
+            Err(e) => {
+                log::warn!("SMT sibling detection failed: {}", e);
+                info
+            }
         }
     }
 
-    fn detect_auto() -> Result<Arc<CoreInfo>, ServiceError> {
+    #[cfg(target_os = "linux")]
+    fn detect_physical_cores() -> Result<Vec<CpuSet>, ServiceError> {
+        SysfsDetector::new().detect_physical_cores()
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    fn detect_physical_cores() -> Result<Vec<CpuSet>, ServiceError> {
+        Ok(Vec::new())
+    }
+
+    /// Add the L3 cache layout, used for the `ccdN` and `l3_largest` core sets
+    fn with_cache_topology(info: CoreInfo) -> CoreInfo {
+        match Self::detect_l3_caches() {
+            Ok(caches) if !caches.is_empty() => {
+                log::info!("Detected {} L3 cache group(s)", caches.len());
+                info.with_l3_caches(caches)
+            }
+            Ok(_) => info,
+            Err(e) => {
+                log::warn!("L3 cache detection failed: {}", e);
+                info
+            }
+        }
+    }
+
+    /// Add the NUMA layout, used for the `numaN` core sets
+    fn with_numa_topology(info: CoreInfo) -> CoreInfo {
+        match Self::detect_numa_nodes() {
+            Ok(nodes) if !nodes.is_empty() => {
+                log::info!("Detected {} NUMA node(s)", nodes.len());
+                info.with_numa_nodes(nodes)
+            }
+            Ok(_) => info,
+            Err(e) => {
+                log::warn!("NUMA node detection failed: {}", e);
+                info
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn detect_numa_nodes() -> Result<Vec<NumaNode>, ServiceError> {
+        SysfsDetector::new().detect_numa_nodes()
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    fn detect_numa_nodes() -> Result<Vec<NumaNode>, ServiceError> {
+        Ok(Vec::new())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn detect_l3_caches() -> Result<Vec<CacheGroup>, ServiceError> {
+        SysfsDetector::new().detect_l3_caches()
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    fn detect_l3_caches() -> Result<Vec<CacheGroup>, ServiceError> {
+        Ok(Vec::new())
+    }
+
+    #[cfg(windows)]
+    fn detect_auto() -> Result<CoreInfo, ServiceError> {
         log::info!("Attempting automatic CPU core detection...");
 
         // Try Windows API detection first
@@ -31,10 +118,36 @@ impl CpuDetector {
         Self::detect_all_cores()
     }
 
-    fn detect_via_windows_api() -> Result<Arc<CoreInfo>, ServiceError> {
+    #[cfg(target_os = "linux")]
+    fn detect_auto() -> Result<CoreInfo, ServiceError> {
+        log::info!("Attempting automatic CPU core detection...");
+
+        match SysfsDetector::new().detect() {
+            Ok(info) => {
+                log::info!("Successfully detected CPU cores via sysfs");
+                return Ok(info);
+            }
+            Err(e) => {
+                log::warn!("sysfs detection failed: {}, falling back to all_cores mode", e);
+            }
+        }
+
+        Self::detect_all_cores()
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    fn detect_auto() -> Result<CoreInfo, ServiceError> {
+        log::info!("No P/E-core detection available on this platform, using all_cores mode");
+        Self::detect_all_cores()
+    }
+
+    /// Raw `SYSTEM_LOGICAL_PROCESSOR_INFORMATION_EX` records for one relationship
+    #[cfg(windows)]
+    fn logical_processor_information(
+        relationship: windows::Win32::System::SystemInformation::LOGICAL_PROCESSOR_RELATIONSHIP,
+    ) -> Result<Vec<u8>, ServiceError> {
         use windows::Win32::System::SystemInformation::{
             GetLogicalProcessorInformationEx,
-            RelationProcessorCore,
             SYSTEM_LOGICAL_PROCESSOR_INFORMATION_EX,
         };
 
@@ -42,7 +155,7 @@ impl CpuDetector {
         let mut buffer_size: u32 = 0;
         unsafe {
             let _ = GetLogicalProcessorInformationEx(
-                RelationProcessorCore,
+                relationship,
                 None,
                 &mut buffer_size,
             );
@@ -58,7 +171,7 @@ impl CpuDetector {
         let mut buffer: Vec<u8> = vec![0; buffer_size as usize];
         let result = unsafe {
             GetLogicalProcessorInformationEx(
-                RelationProcessorCore,
+                relationship,
                 Some(buffer.as_mut_ptr() as *mut SYSTEM_LOGICAL_PROCESSOR_INFORMATION_EX),
                 &mut buffer_size,
             )
@@ -70,82 +183,106 @@ impl CpuDetector {
             ));
         }
 
-        let mut p_cores = Vec::new();
-        let mut e_cores = Vec::new();
-        let mut offset = 0usize;
-        let mut core_index = 0u32;
-
-        while offset < buffer_size as usize {
-            let info = unsafe {
-                &*(buffer.as_ptr().add(offset) as *const SYSTEM_LOGICAL_PROCESSOR_INFORMATION_EX)
-            };
-
-            if info.Relationship == RelationProcessorCore {
-                // Check efficiency class (Windows 11+)
-                // EfficiencyClass: 0 = E-core, 1 = P-core
-                let efficiency_class = unsafe { info.Anonymous.Processor.EfficiencyClass };
-
-                let group_mask = unsafe { &info.Anonymous.Processor.GroupMask };
-                if group_mask.len() > 0 {
-                    let mask = group_mask[0].Mask as usize;
-
-                    // Count logical processors in this core
-                    for bit in 0..64 {
-                        if mask & (1 << bit) != 0 {
-                            if efficiency_class == 1 {
-                                p_cores.push(core_index);
-                            } else {
-                                e_cores.push(core_index);
-                            }
-                            core_index += 1;
-                        }
-                    }
-                }
-            }
+        buffer.truncate(buffer_size as usize);
+        Ok(buffer)
+    }
 
-            offset += info.Size as usize;
-        }
+    #[cfg(windows)]
+    fn detect_via_windows_api() -> Result<CoreInfo, ServiceError> {
+        use windows::Win32::System::SystemInformation::RelationProcessorCore;
 
-        if p_cores.is_empty() && e_cores.is_empty() {
-            return Err(ServiceError::CpuDetection(
-                "No cores detected".to_string()
-            ));
-        }
+        let buffer = Self::logical_processor_information(RelationProcessorCore)?;
+        let records = topology::parse_processor_information(&buffer)?;
+        let info = topology::core_info_from_records(&records)?;
 
-        // If only one type detected, treat as non-hybrid
-        if p_cores.is_empty() {
-            p_cores = e_cores.clone();
-            e_cores.clear();
-        }
+        log::info!("Detected {}", info);
+        Ok(info)
+    }
+
+    #[cfg(windows)]
+    fn detect_physical_cores() -> Result<Vec<CpuSet>, ServiceError> {
+        use windows::Win32::System::SystemInformation::RelationProcessorCore;
 
-        log::info!("Detected P-cores: {:?}, E-cores: {:?}", p_cores, e_cores);
-        Ok(Arc::new(CoreInfo::new(p_cores, e_cores)))
+        let buffer = Self::logical_processor_information(RelationProcessorCore)?;
+        let records = topology::parse_processor_information(&buffer)?;
+        Ok(topology::physical_cores(&records))
     }
 
-    fn detect_manual(p_cores: Vec<u32>, e_cores: Vec<u32>) -> Result<Arc<CoreInfo>, ServiceError> {
+    #[cfg(windows)]
+    fn detect_l3_caches() -> Result<Vec<CacheGroup>, ServiceError> {
+        use windows::Win32::System::SystemInformation::RelationCache;
+
+        let buffer = Self::logical_processor_information(RelationCache)?;
+        let records = topology::parse_processor_information(&buffer)?;
+        Ok(topology::l3_caches(&records))
+    }
+
+    #[cfg(windows)]
+    fn detect_numa_nodes() -> Result<Vec<NumaNode>, ServiceError> {
+        use windows::Win32::System::SystemInformation::RelationNumaNode;
+
+        let buffer = Self::logical_processor_information(RelationNumaNode)?;
+        let records = topology::parse_processor_information(&buffer)?;
+        Ok(topology::numa_nodes(&records))
+    }
+
+    /// Raw `GetLogicalProcessorInformationEx(RelationAll)` buffer, for capturing
+    /// topology fixtures
+    #[cfg(windows)]
+    pub fn dump_topology() -> Result<Vec<u8>, ServiceError> {
+        use windows::Win32::System::SystemInformation::RelationAll;
+
+        Self::logical_processor_information(RelationAll)
+    }
+
+    fn detect_manual(p_cores: CpuSet, e_cores: CpuSet) -> Result<CoreInfo, ServiceError> {
         if p_cores.is_empty() {
             return Err(ServiceError::CpuDetection(
                 "Manual mode requires at least P-cores to be specified".to_string()
             ));
         }
 
-        log::info!("Using manual CPU configuration: P-cores: {:?}, E-cores: {:?}", p_cores, e_cores);
-        Ok(Arc::new(CoreInfo::new(p_cores, e_cores)))
+        log::info!("Using manual CPU configuration: P-cores: {}, E-cores: {}", p_cores, e_cores);
+        Ok(CoreInfo::new(p_cores, e_cores))
     }
 
-    fn detect_all_cores() -> Result<Arc<CoreInfo>, ServiceError> {
-        use windows::Win32::System::SystemInformation::GetSystemInfo;
-        use windows::Win32::System::SystemInformation::SYSTEM_INFO;
+    #[cfg(windows)]
+    fn detect_all_cores() -> Result<CoreInfo, ServiceError> {
+        use windows::Win32::System::Threading::{GetActiveProcessorCount, GetActiveProcessorGroupCount};
 
-        let mut sys_info = SYSTEM_INFO::default();
-        unsafe {
-            GetSystemInfo(&mut sys_info);
+        // Systems with more than 64 logical processors have several groups
+        let mut cores = CpuSet::new();
+        let group_count = unsafe { GetActiveProcessorGroupCount() };
+        for group in 0..group_count {
+            let count = unsafe { GetActiveProcessorCount(group) };
+            for index in 0..count.min(GROUP_SIZE) {
+                cores.insert(group as u32 * GROUP_SIZE + index);
+            }
         }
 
-        let num_processors = sys_info.dwNumberOfProcessors;
+        if cores.is_empty() {
+            return Err(ServiceError::CpuDetection(
+                "Failed to get active processors".to_string()
+            ));
+        }
+
+        log::info!(
+            "Using all_cores mode with {} processors in {} groups",
+            cores.len(),
+            group_count
+        );
+
+        Ok(CoreInfo::all_cores(cores))
+    }
+
+    #[cfg(not(windows))]
+    fn detect_all_cores() -> Result<CoreInfo, ServiceError> {
+        let num_processors = std::thread::available_parallelism()
+            .map(|n| n.get() as u32)
+            .map_err(|e| ServiceError::CpuDetection(format!("Failed to get processor count: {}", e)))?;
         log::info!("Using all_cores mode with {} processors", num_processors);
 
-        Ok(Arc::new(CoreInfo::all_cores(num_processors)))
+        Ok(CoreInfo::all_cores(CpuSet::from_cpus(0..num_processors)))
     }
 }
 
@@ -155,8 +292,8 @@ mod tests {
 
     #[test]
     fn test_manual_detection() {
-        let p_cores = vec![0, 1, 2, 3];
-        let e_cores = vec![4, 5, 6, 7];
+        let p_cores = CpuSet::from_cpus(0..4);
+        let e_cores = CpuSet::from_cpus(4..8);
         let result = CpuDetector::detect(DetectionMode::Manual, p_cores, e_cores);
         assert!(result.is_ok());
 
@@ -167,7 +304,7 @@ mod tests {
 
     #[test]
     fn test_all_cores_detection() {
-        let result = CpuDetector::detect(DetectionMode::AllCores, Vec::new(), Vec::new());
+        let result = CpuDetector::detect(DetectionMode::AllCores, CpuSet::new(), CpuSet::new());
         assert!(result.is_ok());
 
         let info = result.unwrap();
@@ -0,0 +1,173 @@
+use crate::cpu::backend::AffinityBackend;
+use crate::cpu::cpuset::CpuSet;
+use crate::utils::ServiceError;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Affinity backend using `sched_setaffinity`
+///
+/// Linux affinity is per thread, so every thread listed in
+/// `/proc/<pid>/task` is updated. Threads created later inherit the
+/// affinity of the thread that spawns them.
+pub struct LinuxAffinityBackend {
+    proc_root: PathBuf,
+}
+
+impl LinuxAffinityBackend {
+    pub fn new() -> Self {
+        Self::with_proc_root("/proc")
+    }
+
+    /// Enumerate threads from an alternative procfs root
+    pub fn with_proc_root<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            proc_root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Failures that can clear up on a retry, as opposed to a missing
+    /// permission or a CPU set the process may not use
+    fn is_transient(error: &io::Error) -> bool {
+        matches!(error.raw_os_error(), Some(libc::EAGAIN | libc::EBUSY | libc::EINTR | libc::ENOMEM))
+    }
+
+    fn thread_ids(&self, pid: u32) -> Result<Vec<i32>, ServiceError> {
+        let task_dir = self.proc_root.join(pid.to_string()).join("task");
+        let entries = fs::read_dir(&task_dir).map_err(|e| {
+            ServiceError::AffinitySetting(format!(
+                "Failed to list threads of PID: {} ({})",
+                pid, e
+            ))
+        })?;
+
+        Ok(entries
+            .flatten()
+            .filter_map(|entry| entry.file_name().to_str().and_then(|s| s.parse().ok()))
+            .collect())
+    }
+
+    fn to_cpu_set(cpus: &CpuSet) -> Result<libc::cpu_set_t, ServiceError> {
+        let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+        for cpu in cpus {
+            if cpu as usize >= libc::CPU_SETSIZE as usize {
+                return Err(ServiceError::AffinitySetting(format!(
+                    "CPU {} exceeds the maximum of {} CPUs",
+                    cpu,
+                    libc::CPU_SETSIZE
+                )));
+            }
+            unsafe { libc::CPU_SET(cpu as usize, &mut set) };
+        }
+        Ok(set)
+    }
+
+    fn from_cpu_set(set: &libc::cpu_set_t) -> CpuSet {
+        (0..libc::CPU_SETSIZE as u32)
+            .filter(|&cpu| unsafe { libc::CPU_ISSET(cpu as usize, set) })
+            .collect()
+    }
+}
+
+impl Default for LinuxAffinityBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AffinityBackend for LinuxAffinityBackend {
+    fn get_affinity(&self, pid: u32) -> Result<CpuSet, ServiceError> {
+        let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+        let result = unsafe {
+            libc::sched_getaffinity(pid as libc::pid_t, std::mem::size_of::<libc::cpu_set_t>(), &mut set)
+        };
+
+        if result != 0 {
+            return Err(ServiceError::AffinitySetting(format!(
+                "Failed to get affinity mask for PID: {} ({})",
+                pid,
+                io::Error::last_os_error()
+            )));
+        }
+
+        Ok(Self::from_cpu_set(&set))
+    }
+
+    fn set_affinity(&self, pid: u32, cpus: &CpuSet) -> Result<(), ServiceError> {
+        let set = Self::to_cpu_set(cpus)?;
+        let mut updated = 0;
+
+        for tid in self.thread_ids(pid)? {
+            let result = unsafe {
+                libc::sched_setaffinity(tid, std::mem::size_of::<libc::cpu_set_t>(), &set)
+            };
+
+            if result != 0 {
+                let error = io::Error::last_os_error();
+                // The thread exited between listing and updating
+                if error.raw_os_error() == Some(libc::ESRCH) {
+                    continue;
+                }
+                let message = format!(
+                    "Failed to set CPU affinity {} for thread {} of PID: {} ({})",
+                    cpus, tid, pid, error
+                );
+                return Err(if Self::is_transient(&error) {
+                    ServiceError::TransientAffinity(message)
+                } else {
+                    ServiceError::AffinitySetting(message)
+                });
+            }
+            updated += 1;
+        }
+
+        if updated == 0 {
+            return Err(ServiceError::AffinitySetting(format!(
+                "No live threads found for PID: {}",
+                pid
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpu_set_round_trip() {
+        let cpus = CpuSet::from_cpus([1, 2, 5, 7, 64, 300]);
+        let set = LinuxAffinityBackend::to_cpu_set(&cpus).unwrap();
+        assert_eq!(LinuxAffinityBackend::from_cpu_set(&set), cpus);
+
+        assert!(LinuxAffinityBackend::to_cpu_set(&CpuSet::from_cpus([5000])).is_err());
+    }
+
+    #[test]
+    fn test_set_child_affinity() {
+        // A helper process, so the test threads running in parallel keep
+        // their affinity
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let backend = LinuxAffinityBackend::new();
+        let pid = child.id();
+
+        let original = backend.get_affinity(pid).unwrap();
+        assert!(!original.is_empty());
+
+        let lowest = CpuSet::from_cpus(original.first());
+        let pinned = backend.set_affinity(pid, &lowest).and_then(|_| backend.get_affinity(pid));
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert_eq!(pinned.unwrap(), lowest);
+    }
+
+    #[test]
+    fn test_missing_process() {
+        let root = tempfile::tempdir().unwrap();
+        let backend = LinuxAffinityBackend::with_proc_root(root.path());
+        assert!(backend.set_affinity(1234, &CpuSet::from_cpus([0])).is_err());
+    }
+}
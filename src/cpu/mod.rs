@@ -1,7 +1,23 @@
 pub mod affinity;
+pub mod backend;
+pub mod cpuset;
 pub mod detector;
+#[cfg(target_os = "linux")]
+pub mod linux_backend;
+pub mod sysfs;
+pub mod topology;
 pub mod types;
+#[cfg(windows)]
+pub mod windows_backend;
 
 pub use affinity::AffinityManager;
+pub use backend::{default_backend, AffinityBackend, DryRunBackend};
+pub use cpuset::CpuSet;
 pub use detector::CpuDetector;
-pub use types::{CoreInfo, CoreType, DetectionMode};
+#[cfg(target_os = "linux")]
+pub use linux_backend::LinuxAffinityBackend;
+pub use sysfs::SysfsDetector;
+pub use topology::{parse_processor_information, ProcessorRecord};
+pub use types::{CacheGroup, CoreInfo, CoreTarget, CoreType, DetectionMode, NumaNode};
+#[cfg(windows)]
+pub use windows_backend::WindowsAffinityBackend;
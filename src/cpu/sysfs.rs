@@ -0,0 +1,401 @@
+use crate::cpu::cpuset::CpuSet;
+use crate::cpu::types::{CacheGroup, CoreInfo, NumaNode};
+use crate::utils::ServiceError;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// CPUs by efficiency class, as produced by one detection strategy
+type Tiers = BTreeMap<u8, Vec<u32>>;
+
+/// CPU topology detection from Linux sysfs
+///
+/// Sources are tried in order of reliability:
+/// 1. `devices/cpu_core/cpus` and `devices/cpu_atom/cpus` (Intel hybrid PMUs)
+/// 2. `devices/system/cpu/cpuN/cpu_capacity` (ARM big.LITTLE / DynamIQ);
+///    each distinct capacity is its own efficiency class
+/// 3. `topology/thread_siblings_list`: on hybrid parts only P-cores have SMT;
+///    only trusted while every present CPU is online
+///
+/// Anything else is treated as a non-hybrid CPU with every core a P-core.
+///
+/// L3 cache groups come from `devices/system/cpu/cpuN/cache/indexM` and NUMA
+/// nodes from `devices/system/node/nodeN/cpulist`.
+pub struct SysfsDetector {
+    root: PathBuf,
+}
+
+impl SysfsDetector {
+    pub fn new() -> Self {
+        Self::with_root("/sys")
+    }
+
+    /// Read topology from an alternative sysfs root, such as a captured fixture
+    pub fn with_root<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn detect(&self) -> Result<CoreInfo, ServiceError> {
+        let cpus = self.online_cpus()?;
+        if cpus.is_empty() {
+            return Err(ServiceError::CpuDetection("No online CPUs found in sysfs".to_string()));
+        }
+
+        let tiers = if let Some(tiers) = self.detect_hybrid_pmu(&cpus)? {
+            log::info!("Detected hybrid CPU via cpu_core/cpu_atom PMU devices");
+            tiers
+        } else if let Some(tiers) = self.detect_by_capacity(&cpus)? {
+            log::info!("Detected heterogeneous CPU via cpu_capacity");
+            tiers
+        } else if let Some(tiers) = self.detect_by_smt(&cpus)? {
+            log::info!("Detected hybrid CPU via SMT sibling layout");
+            tiers
+        } else {
+            BTreeMap::from([(0, cpus)])
+        };
+
+        let info = CoreInfo::from_efficiency_classes(
+            tiers
+                .into_iter()
+                .map(|(class, cpus)| (class, CpuSet::from_cpus(cpus)))
+                .collect(),
+        );
+
+        log::info!("Detected {}", info);
+        Ok(info)
+    }
+
+    /// L3 caches from `cpuN/cache/indexM`, one entry per distinct `shared_cpu_list`
+    ///
+    /// Returns an empty list when the kernel does not expose cache information.
+    pub fn detect_l3_caches(&self) -> Result<Vec<CacheGroup>, ServiceError> {
+        let mut caches: Vec<CacheGroup> = Vec::new();
+
+        for cpu in self.online_cpus()? {
+            let cache_dir = format!("devices/system/cpu/cpu{}/cache", cpu);
+            let Ok(entries) = fs::read_dir(self.root.join(&cache_dir)) else {
+                continue;
+            };
+
+            let mut indexes: Vec<String> = entries
+                .flatten()
+                .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+                .filter(|name| name.starts_with("index"))
+                .collect();
+            indexes.sort();
+
+            for index in indexes {
+                let dir = format!("{}/{}", cache_dir, index);
+                if self.read_value(&format!("{}/level", dir))? != Some(3) {
+                    continue;
+                }
+
+                let Some(shared) = self.read_cpu_list(&format!("{}/shared_cpu_list", dir))? else {
+                    continue;
+                };
+                let cpus = CpuSet::from_cpus(shared);
+                if caches.iter().any(|c| c.cpus == cpus) {
+                    continue;
+                }
+
+                let size = match self.read_file(&format!("{}/size", dir)) {
+                    Some(size) => parse_cache_size(&size).ok_or_else(|| {
+                        ServiceError::CpuDetection(format!("Invalid cache size {:?} in {}/size", size, dir))
+                    })?,
+                    None => 0,
+                };
+                caches.push(CacheGroup { cpus, size });
+            }
+        }
+
+        Ok(caches)
+    }
+
+    /// SMT sibling groups from `cpuN/topology/thread_siblings_list`
+    ///
+    /// A CPU without sibling information is its own group.
+    pub fn detect_physical_cores(&self) -> Result<Vec<CpuSet>, ServiceError> {
+        let mut cores: Vec<CpuSet> = Vec::new();
+        for cpu in self.online_cpus()? {
+            let path = format!("devices/system/cpu/cpu{}/topology/thread_siblings_list", cpu);
+            let siblings = match self.read_cpu_list(&path)? {
+                Some(siblings) => CpuSet::from_cpus(siblings),
+                None => CpuSet::from_cpus([cpu]),
+            };
+            if !cores.contains(&siblings) {
+                cores.push(siblings);
+            }
+        }
+        Ok(cores)
+    }
+
+    /// NUMA nodes with at least one CPU, from `devices/system/node/nodeN/cpulist`
+    ///
+    /// Returns an empty list on kernels built without NUMA support.
+    pub fn detect_numa_nodes(&self) -> Result<Vec<NumaNode>, ServiceError> {
+        let Ok(entries) = fs::read_dir(self.root.join("devices/system/node")) else {
+            return Ok(Vec::new());
+        };
+
+        let mut node_numbers: Vec<u32> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                name.to_str()
+                    .and_then(|s| s.strip_prefix("node"))
+                    .and_then(|n| n.parse().ok())
+            })
+            .collect();
+        node_numbers.sort_unstable();
+
+        let mut nodes = Vec::new();
+        for node in node_numbers {
+            // Memory-only nodes (CXL, HBM) have an empty cpulist
+            if let Some(cpus) = self.read_cpu_list(&format!("devices/system/node/node{}/cpulist", node))? {
+                if !cpus.is_empty() {
+                    nodes.push(NumaNode { node, cpus: CpuSet::from_cpus(cpus) });
+                }
+            }
+        }
+
+        Ok(nodes)
+    }
+
+    fn online_cpus(&self) -> Result<Vec<u32>, ServiceError> {
+        if let Some(list) = self.read_cpu_list("devices/system/cpu/online")? {
+            return Ok(list);
+        }
+
+        // Older kernels or trimmed fixtures: fall back to the cpuN directories
+        let cpu_dir = self.root.join("devices/system/cpu");
+        let entries = fs::read_dir(&cpu_dir).map_err(|e| {
+            ServiceError::CpuDetection(format!("Failed to read {:?}: {}", cpu_dir, e))
+        })?;
+
+        let mut cpus: Vec<u32> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                name.to_str()
+                    .and_then(|s| s.strip_prefix("cpu"))
+                    .and_then(|n| n.parse().ok())
+            })
+            .collect();
+        cpus.sort_unstable();
+        Ok(cpus)
+    }
+
+    fn detect_hybrid_pmu(&self, cpus: &[u32]) -> Result<Option<Tiers>, ServiceError> {
+        let p_cores = match self.read_cpu_list("devices/cpu_core/cpus")? {
+            Some(list) => list,
+            None => return Ok(None),
+        };
+        let e_cores = self.read_cpu_list("devices/cpu_atom/cpus")?.unwrap_or_default();
+
+        let online: BTreeSet<u32> = cpus.iter().copied().collect();
+        Ok(Some(BTreeMap::from([
+            (1, p_cores.into_iter().filter(|c| online.contains(c)).collect()),
+            (0, e_cores.into_iter().filter(|c| online.contains(c)).collect()),
+        ])))
+    }
+
+    fn detect_by_capacity(&self, cpus: &[u32]) -> Result<Option<Tiers>, ServiceError> {
+        let mut capacities = Vec::with_capacity(cpus.len());
+        for &cpu in cpus {
+            match self.read_value(&format!("devices/system/cpu/cpu{}/cpu_capacity", cpu))? {
+                Some(capacity) => capacities.push((cpu, capacity)),
+                None => return Ok(None),
+            }
+        }
+
+        // Rank the distinct capacities: the smallest is class 0
+        let levels: BTreeSet<u64> = capacities.iter().map(|&(_, c)| c).collect();
+        if levels.len() < 2 {
+            return Ok(None);
+        }
+
+        let mut tiers = Tiers::new();
+        for (cpu, capacity) in capacities {
+            let class = levels.range(..capacity).count().min(u8::MAX as usize) as u8;
+            tiers.entry(class).or_default().push(cpu);
+        }
+        Ok(Some(tiers))
+    }
+
+    fn detect_by_smt(&self, cpus: &[u32]) -> Result<Option<Tiers>, ServiceError> {
+        // A sibling taken offline leaves its partner looking like a core
+        // without SMT, which would pass a non-hybrid CPU off as hybrid
+        if self.read_cpu_list("devices/system/cpu/present")?.as_deref() != Some(cpus) {
+            return Ok(None);
+        }
+
+        let mut p_cores = Vec::new();
+        let mut e_cores = Vec::new();
+
+        for &cpu in cpus {
+            let path = format!("devices/system/cpu/cpu{}/topology/thread_siblings_list", cpu);
+            match self.read_cpu_list(&path)? {
+                Some(siblings) if siblings.len() > 1 => p_cores.push(cpu),
+                Some(_) => e_cores.push(cpu),
+                None => return Ok(None),
+            }
+        }
+
+        // Uniform SMT (or no SMT at all) says nothing about core types
+        if p_cores.is_empty() || e_cores.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(BTreeMap::from([(1, p_cores), (0, e_cores)])))
+    }
+
+    fn read_cpu_list(&self, relative: &str) -> Result<Option<Vec<u32>>, ServiceError> {
+        match self.read_file(relative) {
+            Some(content) => content
+                .parse::<CpuSet>()
+                .map(|cpus| Some(cpus.to_vec()))
+                .map_err(|e| ServiceError::CpuDetection(format!("{} in {}", e, relative))),
+            None => Ok(None),
+        }
+    }
+
+    fn read_value(&self, relative: &str) -> Result<Option<u64>, ServiceError> {
+        match self.read_file(relative) {
+            Some(content) => content.parse().map(Some).map_err(|e| {
+                ServiceError::CpuDetection(format!("Invalid value {:?} in {}: {}", content, relative, e))
+            }),
+            None => Ok(None),
+        }
+    }
+
+    fn read_file(&self, relative: &str) -> Option<String> {
+        fs::read_to_string(self.root.join(relative))
+            .ok()
+            .map(|s| s.trim().to_string())
+    }
+}
+
+impl Default for SysfsDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parse a sysfs cache size such as `32768K`
+fn parse_cache_size(size: &str) -> Option<u64> {
+    let (digits, multiplier) = match size.chars().last()? {
+        'K' => (&size[..size.len() - 1], 1 << 10),
+        'M' => (&size[..size.len() - 1], 1 << 20),
+        'G' => (&size[..size.len() - 1], 1 << 30),
+        _ => (size, 1),
+    };
+    digits.parse::<u64>().ok().map(|n| n * multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> SysfsDetector {
+        SysfsDetector::with_root(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sysfs").join(name))
+    }
+
+    #[test]
+    fn test_intel_hybrid_pmu() {
+        let info = fixture("alder_lake_i7_12700k").detect().unwrap();
+        assert_eq!(info.p_cores, CpuSet::from_cpus(0..16));
+        assert_eq!(info.e_cores, CpuSet::from_cpus(16..20));
+    }
+
+    #[test]
+    fn test_arm_capacity() {
+        let info = fixture("rk3588").detect().unwrap();
+        assert_eq!(info.p_cores, CpuSet::from_cpus(4..8));
+        assert_eq!(info.e_cores, CpuSet::from_cpus(0..4));
+    }
+
+    #[test]
+    fn test_arm_three_capacity_tiers() {
+        let info = fixture("sm8550").detect().unwrap();
+        assert_eq!(info.tier(0), Some(&CpuSet::from_cpus(0..3)));
+        assert_eq!(info.tier(1), Some(&CpuSet::from_cpus(3..7)));
+        assert_eq!(info.highest_tier(), CpuSet::from_cpus([7]));
+        assert_eq!(info.p_cores, CpuSet::from_cpus([7]));
+        assert_eq!(info.e_cores, CpuSet::from_cpus(0..7));
+    }
+
+    #[test]
+    fn test_smt_heuristic() {
+        let info = fixture("alder_lake_i5_1235u_no_pmu").detect().unwrap();
+        assert_eq!(info.p_cores, CpuSet::from_cpus(0..4));
+        assert_eq!(info.e_cores, CpuSet::from_cpus(4..12));
+    }
+
+    #[test]
+    fn test_non_hybrid() {
+        let info = fixture("ryzen7_7700x").detect().unwrap();
+        assert_eq!(info.total_cores, 16);
+        assert!(!info.has_hybrid_architecture());
+
+        // CPUs 8-11 offline leave 0-3 without an online sibling
+        let info = fixture("ryzen7_7700x_smt_offline").detect().unwrap();
+        assert_eq!(info.total_cores, 12);
+        assert!(!info.has_hybrid_architecture());
+    }
+
+    #[test]
+    fn test_x3d_l3_caches() {
+        let detector = fixture("ryzen9_7950x3d");
+        let caches = detector.detect_l3_caches().unwrap();
+        assert_eq!(caches.len(), 2);
+
+        let info = detector.detect().unwrap().with_l3_caches(caches);
+        let vcache = CpuSet::from_cpus((0..8).chain(16..24));
+        assert_eq!(info.named_set("ccd0"), Some(&vcache));
+        assert_eq!(info.named_set("ccd1"), Some(&CpuSet::from_cpus((8..16).chain(24..32))));
+        assert_eq!(info.named_set("l3_largest"), Some(&vcache));
+        assert_eq!(info.l3_caches()[0].size, 96 << 20);
+    }
+
+    #[test]
+    fn test_single_l3_and_missing_cache_info() {
+        let caches = fixture("ryzen7_7700x").detect_l3_caches().unwrap();
+        assert_eq!(caches, vec![CacheGroup { cpus: CpuSet::from_cpus(0..16), size: 32 << 20 }]);
+
+        assert!(fixture("rk3588").detect_l3_caches().unwrap().is_empty());
+        assert_eq!(parse_cache_size("1024K"), Some(1 << 20));
+        assert_eq!(parse_cache_size("abc"), None);
+    }
+
+    #[test]
+    fn test_physical_cores() {
+        let cores = fixture("alder_lake_i7_12700k").detect_physical_cores().unwrap();
+        assert_eq!(cores.len(), 12);
+        assert!(cores.contains(&CpuSet::from_cpus([0, 1])));
+        assert!(cores.contains(&CpuSet::from_cpus([19])));
+
+        // Linux numbers AMD SMT siblings n and n+8
+        let info = fixture("ryzen7_7700x").detect().unwrap();
+        let info = info.with_physical_cores(fixture("ryzen7_7700x").detect_physical_cores().unwrap());
+        assert_eq!(info.resolve(&"p_cores_physical".parse().unwrap()), CpuSet::from_cpus(0..8));
+    }
+
+    #[test]
+    fn test_two_socket_numa_nodes() {
+        let nodes = fixture("xeon_gold_6248r_2s").detect_numa_nodes().unwrap();
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].cpus, CpuSet::from_cpus((0..24).chain(48..72)));
+        assert_eq!(nodes[1].node, 1);
+        assert_eq!(nodes[1].cpus, CpuSet::from_cpus((24..48).chain(72..96)));
+
+        // Fixtures without a node directory have no NUMA information
+        assert!(fixture("rk3588").detect_numa_nodes().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_missing_root() {
+        assert!(SysfsDetector::with_root("/nonexistent/sysfs").detect().is_err());
+    }
+}
@@ -0,0 +1,328 @@
+use crate::cpu::cpuset::CpuSet;
+use crate::cpu::types::{CacheGroup, CoreInfo, NumaNode};
+use crate::utils::ServiceError;
+use std::collections::BTreeMap;
+
+// LOGICAL_PROCESSOR_RELATIONSHIP values
+const RELATION_PROCESSOR_CORE: u32 = 0;
+const RELATION_NUMA_NODE: u32 = 1;
+const RELATION_CACHE: u32 = 2;
+const RELATION_NUMA_NODE_EX: u32 = 6;
+
+/// `LTP_PC_SMT`: the core runs more than one logical processor
+const LTP_PC_SMT: u8 = 1;
+
+/// Size of the `Relationship` and `Size` header of every record
+const HEADER_SIZE: usize = 8;
+/// Size of a `GROUP_AFFINITY` entry on 64-bit Windows
+const GROUP_AFFINITY_SIZE: usize = 16;
+
+/// One record of a `SYSTEM_LOGICAL_PROCESSOR_INFORMATION_EX` buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessorRecord {
+    /// `RelationProcessorCore`: one physical core
+    Core {
+        efficiency_class: u8,
+        smt: bool,
+        cpus: CpuSet,
+    },
+    /// `RelationCache`
+    Cache {
+        level: u8,
+        /// Size in bytes
+        size: u32,
+        cpus: CpuSet,
+    },
+    /// `RelationNumaNode`
+    NumaNode { node: u32, cpus: CpuSet },
+    /// Any relationship the detector does not use
+    Other { relationship: u32 },
+}
+
+/// Parse the buffer filled by `GetLogicalProcessorInformationEx`
+///
+/// Works on the raw bytes (64-bit layout) rather than the `windows` crate
+/// structs, so captured buffers can be tested on any platform. Logical
+/// processors are numbered by their bit position within each group's mask.
+pub fn parse_processor_information(buffer: &[u8]) -> Result<Vec<ProcessorRecord>, ServiceError> {
+    let mut records = Vec::new();
+    let mut offset = 0usize;
+
+    while offset < buffer.len() {
+        let relationship = read_u32(buffer, offset)?;
+        let size = read_u32(buffer, offset + 4)? as usize;
+        if size < HEADER_SIZE || offset + size > buffer.len() {
+            return Err(ServiceError::CpuDetection(format!(
+                "Invalid processor information record size {} at offset {}",
+                size, offset
+            )));
+        }
+
+        let record = &buffer[offset..offset + size];
+        records.push(match relationship {
+            RELATION_PROCESSOR_CORE => {
+                // PROCESSOR_RELATIONSHIP: Flags, EfficiencyClass, Reserved[20],
+                // GroupCount, GroupMask[GroupCount]
+                let flags = read_u8(record, 8)?;
+                let efficiency_class = read_u8(record, 9)?;
+                let group_count = read_u16(record, 30)?;
+                ProcessorRecord::Core {
+                    efficiency_class,
+                    smt: flags & LTP_PC_SMT != 0,
+                    cpus: read_group_masks(record, 32, group_count)?,
+                }
+            }
+            RELATION_CACHE => {
+                // CACHE_RELATIONSHIP: Level, Associativity, LineSize, CacheSize,
+                // Type, Reserved[18], GroupCount, GroupMask[GroupCount]
+                let level = read_u8(record, 8)?;
+                let size = read_u32(record, 12)?;
+                // GroupCount is 0 before Windows 11, with a single GroupMask
+                let group_count = read_u16(record, 38)?.max(1);
+                ProcessorRecord::Cache {
+                    level,
+                    size,
+                    cpus: read_group_masks(record, 40, group_count)?,
+                }
+            }
+            RELATION_NUMA_NODE | RELATION_NUMA_NODE_EX => {
+                // NUMA_NODE_RELATIONSHIP: NodeNumber, Reserved[18], GroupCount,
+                // GroupMask[GroupCount]; GroupCount is 0 before Windows 11
+                let node = read_u32(record, 8)?;
+                let group_count = read_u16(record, 30)?.max(1);
+                ProcessorRecord::NumaNode {
+                    node,
+                    cpus: read_group_masks(record, 32, group_count)?,
+                }
+            }
+            relationship => ProcessorRecord::Other { relationship },
+        });
+
+        offset += size;
+    }
+
+    Ok(records)
+}
+
+/// Build `CoreInfo` from the efficiency class of every core record
+pub fn core_info_from_records(records: &[ProcessorRecord]) -> Result<CoreInfo, ServiceError> {
+    let mut classes: BTreeMap<u8, CpuSet> = BTreeMap::new();
+    for record in records {
+        if let ProcessorRecord::Core { efficiency_class, cpus, .. } = record {
+            let tier = classes.entry(*efficiency_class).or_default();
+            *tier = tier.union(cpus);
+        }
+    }
+
+    // A single class means a non-hybrid CPU: every core is a P-core
+    let info = CoreInfo::from_efficiency_classes(classes);
+    if info.total_cores == 0 {
+        return Err(ServiceError::CpuDetection("No cores detected".to_string()));
+    }
+    Ok(info)
+}
+
+/// L3 caches among the records
+pub fn l3_caches(records: &[ProcessorRecord]) -> Vec<CacheGroup> {
+    records
+        .iter()
+        .filter_map(|record| match record {
+            ProcessorRecord::Cache { level: 3, size, cpus } => Some(CacheGroup {
+                cpus: cpus.clone(),
+                size: *size as u64,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Logical processors of each physical core among the records
+pub fn physical_cores(records: &[ProcessorRecord]) -> Vec<CpuSet> {
+    records
+        .iter()
+        .filter_map(|record| match record {
+            ProcessorRecord::Core { cpus, .. } => Some(cpus.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// NUMA nodes among the records
+pub fn numa_nodes(records: &[ProcessorRecord]) -> Vec<NumaNode> {
+    records
+        .iter()
+        .filter_map(|record| match record {
+            ProcessorRecord::NumaNode { node, cpus } => Some(NumaNode {
+                node: *node,
+                cpus: cpus.clone(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn read_group_masks(record: &[u8], offset: usize, count: u16) -> Result<CpuSet, ServiceError> {
+    let mut cpus = CpuSet::new();
+    for index in 0..count as usize {
+        // GROUP_AFFINITY: Mask (KAFFINITY), Group, Reserved[3]
+        let entry = offset + index * GROUP_AFFINITY_SIZE;
+        let mask = read_u64(record, entry)?;
+        let group = read_u16(record, entry + 8)?;
+        cpus = cpus.union(&CpuSet::from_group_mask(group, mask));
+    }
+    Ok(cpus)
+}
+
+fn read_bytes<const N: usize>(buffer: &[u8], offset: usize) -> Result<[u8; N], ServiceError> {
+    buffer
+        .get(offset..offset + N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| {
+            ServiceError::CpuDetection(format!(
+                "Processor information buffer truncated at offset {}",
+                offset
+            ))
+        })
+}
+
+fn read_u8(buffer: &[u8], offset: usize) -> Result<u8, ServiceError> {
+    Ok(read_bytes::<1>(buffer, offset)?[0])
+}
+
+fn read_u16(buffer: &[u8], offset: usize) -> Result<u16, ServiceError> {
+    Ok(u16::from_le_bytes(read_bytes(buffer, offset)?))
+}
+
+fn read_u32(buffer: &[u8], offset: usize) -> Result<u32, ServiceError> {
+    Ok(u32::from_le_bytes(read_bytes(buffer, offset)?))
+}
+
+fn read_u64(buffer: &[u8], offset: usize) -> Result<u64, ServiceError> {
+    Ok(u64::from_le_bytes(read_bytes(buffer, offset)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// A buffer from `tests/fixtures/windows`; these are reconstructed from
+    /// documented topologies, not captured, so they check the parser against
+    /// our reading of the layout rather than against real machines
+    fn fixture(name: &str) -> Vec<ProcessorRecord> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/windows").join(name);
+        parse_processor_information(&std::fs::read(path).unwrap()).unwrap()
+    }
+
+    /// A `RelationProcessorCore` record with one group mask
+    fn core_record(efficiency_class: u8, smt: bool, group: u16, mask: u64) -> Vec<u8> {
+        let mut record = vec![0u8; 48];
+        record[0..4].copy_from_slice(&RELATION_PROCESSOR_CORE.to_le_bytes());
+        record[4..8].copy_from_slice(&48u32.to_le_bytes());
+        record[8] = if smt { LTP_PC_SMT } else { 0 };
+        record[9] = efficiency_class;
+        record[30..32].copy_from_slice(&1u16.to_le_bytes());
+        record[32..40].copy_from_slice(&mask.to_le_bytes());
+        record[40..42].copy_from_slice(&group.to_le_bytes());
+        record
+    }
+
+    #[test]
+    fn test_uses_bit_positions_not_enumeration_order() {
+        // Cores listed out of order, one of them in processor group 1
+        let buffer = [
+            core_record(1, true, 0, 0b1100_0000),
+            core_record(0, false, 1, 0b10),
+            core_record(1, true, 0, 0b0000_0011),
+        ]
+        .concat();
+
+        let records = parse_processor_information(&buffer).unwrap();
+        assert_eq!(
+            records[0],
+            ProcessorRecord::Core { efficiency_class: 1, smt: true, cpus: CpuSet::from_cpus([6, 7]) }
+        );
+
+        let info = core_info_from_records(&records).unwrap();
+        assert_eq!(info.p_cores, CpuSet::from_cpus([0, 1, 6, 7]));
+        assert_eq!(info.e_cores, CpuSet::from_cpus([65]));
+    }
+
+    #[test]
+    fn test_numa_nodes_across_groups() {
+        // Two sockets, one processor group each, on Windows 10 (GroupCount 0)
+        let node_record = |node: u32, group: u16| {
+            let mut record = vec![0u8; 48];
+            record[0..4].copy_from_slice(&RELATION_NUMA_NODE.to_le_bytes());
+            record[4..8].copy_from_slice(&48u32.to_le_bytes());
+            record[8..12].copy_from_slice(&node.to_le_bytes());
+            record[32..40].copy_from_slice(&u64::MAX.to_le_bytes());
+            record[40..42].copy_from_slice(&group.to_le_bytes());
+            record
+        };
+
+        let records = parse_processor_information(&[node_record(0, 0), node_record(1, 1)].concat()).unwrap();
+        let nodes = numa_nodes(&records);
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[1].node, 1);
+        assert_eq!(nodes[1].cpus, CpuSet::from_cpus(64..128));
+    }
+
+    #[test]
+    fn test_rejects_truncated_buffers() {
+        let record = core_record(0, false, 0, 1);
+        assert!(parse_processor_information(&record[..40]).is_err());
+
+        let mut bad_size = record.clone();
+        bad_size[4..8].copy_from_slice(&4u32.to_le_bytes());
+        assert!(parse_processor_information(&bad_size).is_err());
+
+        assert!(core_info_from_records(&[]).is_err());
+    }
+
+    #[test]
+    fn test_alder_lake_reconstructed() {
+        let records = fixture("alder_lake_i7_12700k.bin");
+        let info = core_info_from_records(&records).unwrap();
+
+        // 8 P-cores with Hyper-Threading, then 4 E-cores
+        assert_eq!(info.p_cores, CpuSet::from_cpus(0..16));
+        assert_eq!(info.e_cores, CpuSet::from_cpus(16..20));
+        assert_eq!(records.iter().filter(|r| matches!(r, ProcessorRecord::Core { smt: true, .. })).count(), 8);
+
+        let info = info.with_physical_cores(physical_cores(&records));
+        assert_eq!(info.physical_cores().len(), 12);
+        assert_eq!(info.physical_cores()[1], CpuSet::from_cpus([2, 3]));
+        assert_eq!(
+            info.resolve(&"p_cores_physical".parse().unwrap()),
+            CpuSet::from_cpus((0..16).step_by(2))
+        );
+        assert_eq!(l3_caches(&records), vec![CacheGroup { cpus: CpuSet::from_cpus(0..20), size: 25 << 20 }]);
+        assert_eq!(numa_nodes(&records), vec![NumaNode { node: 0, cpus: CpuSet::from_cpus(0..20) }]);
+    }
+
+    #[test]
+    fn test_raptor_lake_reconstructed() {
+        let records = fixture("raptor_lake_i9_13900k.bin");
+        let info = core_info_from_records(&records).unwrap();
+
+        assert_eq!(info.total_cores, 32);
+        assert_eq!(info.p_cores, CpuSet::from_cpus(0..16));
+        assert_eq!(info.e_cores, CpuSet::from_cpus(16..32));
+        assert_eq!(l3_caches(&records)[0].size, 36 << 20);
+    }
+
+    #[test]
+    fn test_zen4_reconstructed() {
+        let records = fixture("zen4_ryzen9_7950x.bin");
+        let info = core_info_from_records(&records).unwrap().with_l3_caches(l3_caches(&records));
+
+        // Every core has the same efficiency class: not hybrid
+        assert_eq!(info.p_cores, CpuSet::from_cpus(0..32));
+        assert!(!info.has_hybrid_architecture());
+
+        // Windows numbers SMT siblings adjacently, so each CCD is contiguous
+        assert_eq!(info.named_set("ccd0"), Some(&CpuSet::from_cpus(0..16)));
+        assert_eq!(info.named_set("ccd1"), Some(&CpuSet::from_cpus(16..32)));
+    }
+}
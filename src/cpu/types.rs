@@ -1,4 +1,9 @@
+use crate::cpu::cpuset::CpuSet;
+use crate::utils::ServiceError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
 use std::fmt;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CoreType {
@@ -7,81 +12,461 @@ pub enum CoreType {
     Unknown,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum DetectionMode {
+    #[default]
     Auto,
     Manual,
     AllCores,
 }
 
-impl DetectionMode {
-    pub fn from_str(s: &str) -> Self {
+impl FromStr for DetectionMode {
+    type Err = ServiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
-            "manual" => DetectionMode::Manual,
-            "all_cores" | "allcores" => DetectionMode::AllCores,
-            _ => DetectionMode::Auto,
+            "auto" => Ok(DetectionMode::Auto),
+            "manual" => Ok(DetectionMode::Manual),
+            "all_cores" | "allcores" => Ok(DetectionMode::AllCores),
+            _ => Err(ServiceError::Config(format!(
+                "Unknown detection_mode {:?}; expected auto, manual or all_cores",
+                s
+            ))),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+impl fmt::Display for DetectionMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DetectionMode::Auto => write!(f, "auto"),
+            DetectionMode::Manual => write!(f, "manual"),
+            DetectionMode::AllCores => write!(f, "all_cores"),
+        }
+    }
+}
+
+impl Serialize for DetectionMode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DetectionMode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CoreInfo {
-    /// List of P-core (Performance cores) indices
-    pub p_cores: Vec<u32>,
-    /// List of E-core (Efficient cores) indices
-    pub e_cores: Vec<u32>,
+    /// P-core (Performance cores) logical processors: the highest efficiency class
+    pub p_cores: CpuSet,
+    /// E-core (Efficient cores) logical processors: every lower efficiency class
+    pub e_cores: CpuSet,
     /// Total number of logical processors
     pub total_cores: u32,
-    /// Bitmask for P-cores
-    pub p_core_mask: usize,
-    /// Bitmask for E-cores
-    pub e_core_mask: usize,
+    /// Logical processors by efficiency class; a higher class is faster
+    tiers: BTreeMap<u8, CpuSet>,
+    /// L3 caches, ordered by their lowest processor
+    l3_caches: Vec<CacheGroup>,
+    /// NUMA nodes, ordered by node number
+    numa_nodes: Vec<NumaNode>,
+    /// SMT sibling groups: the logical processors of each physical core,
+    /// ordered by their lowest processor
+    physical_cores: Vec<CpuSet>,
+    /// Core sets a target can refer to by name, such as `ccd0` or `l3_largest`
+    named_sets: BTreeMap<String, CpuSet>,
+}
+
+/// Logical processors of one NUMA node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NumaNode {
+    pub node: u32,
+    pub cpus: CpuSet,
+}
+
+/// Logical processors sharing one cache
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheGroup {
+    pub cpus: CpuSet,
+    /// Cache size in bytes
+    pub size: u64,
 }
 
 impl CoreInfo {
-    pub fn new(p_cores: Vec<u32>, e_cores: Vec<u32>) -> Self {
-        let total_cores = p_cores.len() + e_cores.len();
-        let p_core_mask = Self::calculate_mask(&p_cores);
-        let e_core_mask = Self::calculate_mask(&e_cores);
+    /// Two-tier topology: P-cores are class 1 and E-cores class 0
+    pub fn new(p_cores: CpuSet, e_cores: CpuSet) -> Self {
+        let total_cores = p_cores.union(&e_cores).len() as u32;
+
+        let mut tiers = BTreeMap::new();
+        if !e_cores.is_empty() {
+            tiers.insert(0, e_cores.clone());
+        }
+        if !p_cores.is_empty() {
+            tiers.insert(if e_cores.is_empty() { 0 } else { 1 }, p_cores.clone());
+        }
 
         Self {
             p_cores,
             e_cores,
-            total_cores: total_cores as u32,
-            p_core_mask,
-            e_core_mask,
+            total_cores,
+            tiers,
+            l3_caches: Vec::new(),
+            numa_nodes: Vec::new(),
+            physical_cores: Vec::new(),
+            named_sets: BTreeMap::new(),
         }
     }
 
-    pub fn all_cores(count: u32) -> Self {
-        let cores: Vec<u32> = (0..count).collect();
-        let mask = Self::calculate_mask(&cores);
+    /// Build from the efficiency class of every logical processor
+    ///
+    /// The highest class becomes the P-cores and all others the E-cores, so
+    /// a three-tier part (P, E and low-power E) still has a P/E view.
+    pub fn from_efficiency_classes(tiers: BTreeMap<u8, CpuSet>) -> Self {
+        let tiers: BTreeMap<u8, CpuSet> = tiers.into_iter().filter(|(_, cpus)| !cpus.is_empty()).collect();
+        let p_cores = tiers.values().next_back().cloned().unwrap_or_default();
+        let all = tiers.values().fold(CpuSet::new(), |all, cpus| all.union(cpus));
+        let e_cores = all.difference(&p_cores);
 
         Self {
-            p_cores: cores.clone(),
-            e_cores: Vec::new(),
-            total_cores: count,
-            p_core_mask: mask,
-            e_core_mask: 0,
+            p_cores,
+            e_cores,
+            total_cores: all.len() as u32,
+            tiers,
+            l3_caches: Vec::new(),
+            numa_nodes: Vec::new(),
+            physical_cores: Vec::new(),
+            named_sets: BTreeMap::new(),
         }
     }
 
-    fn calculate_mask(cores: &[u32]) -> usize {
-        cores.iter().fold(0, |mask, &core| mask | (1 << core))
+    /// Record the L3 cache layout
+    ///
+    /// Each L3 becomes a named set `ccd0`, `ccd1`, ... in order of its lowest
+    /// processor, and `l3_largest` holds the processors of the largest L3
+    /// (every L3 of that size, so it is all processors on uniform parts).
+    /// On a 7950X3D, `l3_largest` is the V-Cache CCD.
+    pub fn with_l3_caches(mut self, caches: Vec<CacheGroup>) -> Self {
+        let mut caches: Vec<CacheGroup> = caches.into_iter().filter(|c| !c.cpus.is_empty()).collect();
+        caches.sort_by_key(|c| c.cpus.first());
+        caches.dedup_by(|a, b| a.cpus == b.cpus);
+
+        self.named_sets.retain(|name, _| !(name.starts_with("ccd") || name == "l3_largest"));
+        if let Some(largest) = caches.iter().map(|c| c.size).max() {
+            let cpus = caches
+                .iter()
+                .filter(|c| c.size == largest)
+                .fold(CpuSet::new(), |all, c| all.union(&c.cpus));
+            self.named_sets.insert("l3_largest".to_string(), cpus);
+        }
+        for (index, cache) in caches.iter().enumerate() {
+            self.named_sets.insert(format!("ccd{}", index), cache.cpus.clone());
+        }
+
+        self.l3_caches = caches;
+        self
+    }
+
+    /// Record the NUMA layout; each node becomes a named set `numaN`
+    pub fn with_numa_nodes(mut self, nodes: Vec<NumaNode>) -> Self {
+        let mut nodes: Vec<NumaNode> = nodes.into_iter().filter(|n| !n.cpus.is_empty()).collect();
+        nodes.sort_by_key(|n| n.node);
+        nodes.dedup_by_key(|n| n.node);
+
+        self.named_sets.retain(|name, _| !name.starts_with("numa"));
+        for node in &nodes {
+            self.named_sets.insert(format!("numa{}", node.node), node.cpus.clone());
+        }
+
+        self.numa_nodes = nodes;
+        self
+    }
+
+    /// NUMA nodes, ordered by node number
+    pub fn numa_nodes(&self) -> &[NumaNode] {
+        &self.numa_nodes
+    }
+
+    /// Restrict a set to the single NUMA node holding most of it
+    ///
+    /// Nodes with an equal share are chosen between by `pid`, so processes
+    /// spread over the nodes instead of all landing on the first one. Sets
+    /// within one node, and machines with one node, are returned unchanged.
+    pub fn confine_to_numa_node(&self, cpus: &CpuSet, pid: u32) -> CpuSet {
+        let shares: Vec<CpuSet> = self
+            .numa_nodes
+            .iter()
+            .map(|node| node.cpus.intersection(cpus))
+            .filter(|share| !share.is_empty())
+            .collect();
+
+        if shares.len() < 2 {
+            return cpus.clone();
+        }
+
+        let largest = shares.iter().map(CpuSet::len).max().unwrap_or(0);
+        let candidates: Vec<&CpuSet> = shares.iter().filter(|share| share.len() == largest).collect();
+        candidates[pid as usize % candidates.len()].clone()
+    }
+
+    /// Record which logical processors share a physical core
+    pub fn with_physical_cores(mut self, cores: Vec<CpuSet>) -> Self {
+        let mut cores: Vec<CpuSet> = cores.into_iter().filter(|c| !c.is_empty()).collect();
+        cores.sort_by_key(CpuSet::first);
+        cores.dedup();
+        self.physical_cores = cores;
+        self
+    }
+
+    /// SMT sibling groups, one per physical core
+    pub fn physical_cores(&self) -> &[CpuSet] {
+        &self.physical_cores
+    }
+
+    /// One logical processor per physical core: the lowest of each core's
+    /// threads in `cpus`
+    ///
+    /// Processors with no known sibling group are kept, so this is a no-op
+    /// when the SMT layout is unknown.
+    pub fn first_threads(&self, cpus: &CpuSet) -> CpuSet {
+        let mut result = cpus.clone();
+        for core in &self.physical_cores {
+            let threads = core.intersection(cpus);
+            if let Some(first) = threads.first() {
+                result = result.difference(&threads);
+                result.insert(first);
+            }
+        }
+        result
+    }
+
+    /// L3 caches, ordered by their lowest processor
+    pub fn l3_caches(&self) -> &[CacheGroup] {
+        &self.l3_caches
+    }
+
+    /// A named core set, such as `ccd0` or `l3_largest`
+    pub fn named_set(&self, name: &str) -> Option<&CpuSet> {
+        self.named_sets.get(name)
+    }
+
+    /// All named core sets
+    pub fn named_sets(&self) -> impl Iterator<Item = (&str, &CpuSet)> {
+        self.named_sets.iter().map(|(name, cpus)| (name.as_str(), cpus))
+    }
+
+    /// Treat every logical processor as a P-core
+    pub fn all_cores(cores: CpuSet) -> Self {
+        Self::new(cores, CpuSet::new())
+    }
+
+    /// All logical processors, P-cores and E-cores
+    pub fn all(&self) -> CpuSet {
+        self.p_cores.union(&self.e_cores)
     }
 
     pub fn has_hybrid_architecture(&self) -> bool {
         !self.p_cores.is_empty() && !self.e_cores.is_empty()
     }
+
+    /// Efficiency classes and their processors, slowest first
+    pub fn tiers(&self) -> impl DoubleEndedIterator<Item = (u8, &CpuSet)> {
+        self.tiers.iter().map(|(&class, cpus)| (class, cpus))
+    }
+
+    /// Processors of one efficiency class
+    pub fn tier(&self, class: u8) -> Option<&CpuSet> {
+        self.tiers.get(&class)
+    }
+
+    /// Processors of the fastest class
+    pub fn highest_tier(&self) -> CpuSet {
+        self.tiers.values().next_back().cloned().unwrap_or_default()
+    }
+
+    /// Processors of the most efficient class
+    pub fn lowest_tier(&self) -> CpuSet {
+        self.tiers.values().next().cloned().unwrap_or_default()
+    }
+
+    /// Efficiency class of a logical processor
+    pub fn efficiency_class(&self, cpu: u32) -> Option<u8> {
+        self.tiers
+            .iter()
+            .find(|(_, cpus)| cpus.contains(cpu))
+            .map(|(&class, _)| class)
+    }
+
+    /// Whether a target selects at least one processor on this machine
+    ///
+    /// Fails for core sets that were not detected, such as `ccd1` on a
+    /// single-CCD part, and for `e_cores` or `tierN` on CPUs without them.
+    pub fn can_resolve(&self, target: &CoreTarget) -> bool {
+        !self.resolve(target).is_empty()
+    }
+
+    /// Processors selected by a target
+    pub fn resolve(&self, target: &CoreTarget) -> CpuSet {
+        match target {
+            CoreTarget::PCores => self.p_cores.clone(),
+            CoreTarget::ECores => self.e_cores.clone(),
+            CoreTarget::AllCores => self.all(),
+            CoreTarget::HighestTier => self.highest_tier(),
+            CoreTarget::LowestTier => self.lowest_tier(),
+            CoreTarget::Tier(class) => self.tier(*class).cloned().unwrap_or_default(),
+            CoreTarget::Named(name) => self.named_set(name).cloned().unwrap_or_default(),
+            CoreTarget::Cpus(cpus) => cpus.clone(),
+            CoreTarget::Physical(inner) => self.first_threads(&self.resolve(inner)),
+        }
+    }
 }
 
 impl fmt::Display for CoreInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "CoreInfo {{ total: {}, P-cores: {:?} (mask: 0x{:X}), E-cores: {:?} (mask: 0x{:X}) }}",
-            self.total_cores, self.p_cores, self.p_core_mask, self.e_cores, self.e_core_mask
-        )
+            "CoreInfo {{ total: {}, P-cores: {}, E-cores: {}",
+            self.total_cores, self.p_cores, self.e_cores
+        )?;
+
+        if self.tiers.len() > 2 {
+            let tiers: Vec<String> = self
+                .tiers()
+                .rev()
+                .map(|(class, cpus)| format!("{}: {}", class, cpus))
+                .collect();
+            write!(f, ", tiers: [{}]", tiers.join("; "))?;
+        }
+
+        if self.physical_cores.iter().any(|core| core.len() > 1) {
+            write!(f, ", physical cores: {}", self.physical_cores.len())?;
+        }
+
+        if self.l3_caches.len() > 1 {
+            let caches: Vec<String> = self
+                .l3_caches
+                .iter()
+                .map(|c| format!("{} ({} MB)", c.cpus, c.size / (1024 * 1024)))
+                .collect();
+            write!(f, ", L3: [{}]", caches.join("; "))?;
+        }
+
+        if self.numa_nodes.len() > 1 {
+            let nodes: Vec<String> = self
+                .numa_nodes
+                .iter()
+                .map(|n| format!("{}: {}", n.node, n.cpus))
+                .collect();
+            write!(f, ", NUMA: [{}]", nodes.join("; "))?;
+        }
+
+        write!(f, " }}")
+    }
+}
+
+/// Which processors a process is pinned to
+///
+/// Written in the config as `"p_cores"`, `"e_cores"`, `"all_cores"`,
+/// `"tier_highest"`, `"tier_lowest"`, `"tierN"` (efficiency class `N`), a
+/// named core set such as `"l3_largest"`, `"ccd1"` or `"numa0"`, or an explicit CPU
+/// list such as `"0-7"`. Any named target can take a `_physical` suffix,
+/// such as `"p_cores_physical"`, to use only the first hardware thread of
+/// each physical core.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum CoreTarget {
+    #[default]
+    PCores,
+    ECores,
+    AllCores,
+    HighestTier,
+    LowestTier,
+    Tier(u8),
+    /// A named core set from detection; see `CoreInfo::named_set`
+    Named(String),
+    Cpus(CpuSet),
+    /// The first hardware thread of each physical core in the inner target
+    Physical(Box<CoreTarget>),
+}
+
+impl FromStr for CoreTarget {
+    type Err = ServiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+        if let Some(inner) = name.strip_suffix("_physical") {
+            return match inner.parse()? {
+                CoreTarget::Cpus(_) | CoreTarget::Physical(_) => {
+                    Err(ServiceError::Config(format!("Invalid core target {:?}", s)))
+                }
+                inner => Ok(CoreTarget::Physical(Box::new(inner))),
+            };
+        }
+
+        let target = match name.as_str() {
+            "p_cores" | "pcores" => CoreTarget::PCores,
+            "e_cores" | "ecores" => CoreTarget::ECores,
+            "all_cores" | "allcores" => CoreTarget::AllCores,
+            "tier_highest" => CoreTarget::HighestTier,
+            "tier_lowest" => CoreTarget::LowestTier,
+            _ => match name.strip_prefix("tier") {
+                Some(class) => CoreTarget::Tier(class.parse().map_err(|_| {
+                    ServiceError::Config(format!("Invalid efficiency class in core target {:?}", s))
+                })?),
+                None if is_core_set_name(&name) => CoreTarget::Named(name),
+                None if name.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                    return Err(ServiceError::Config(format!(
+                        "Unknown core target {:?}; expected p_cores, e_cores, all_cores, tier_highest, \
+                         tier_lowest, tierN, l3_largest, ccdN, numaN or a CPU list",
+                        s
+                    )));
+                }
+                None => CoreTarget::Cpus(s.parse().map_err(|e| {
+                    ServiceError::Config(format!("Unknown core target {:?}: {}", s, e))
+                })?),
+            },
+        };
+        Ok(target)
+    }
+}
+
+/// Whether `name` has the form of a core set detection can name:
+/// `l3_largest`, `ccdN` or `numaN`
+fn is_core_set_name(name: &str) -> bool {
+    let numbered = |prefix: &str| {
+        name.strip_prefix(prefix)
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+    };
+    name == "l3_largest" || numbered("ccd") || numbered("numa")
+}
+
+impl fmt::Display for CoreTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoreTarget::PCores => write!(f, "p_cores"),
+            CoreTarget::ECores => write!(f, "e_cores"),
+            CoreTarget::AllCores => write!(f, "all_cores"),
+            CoreTarget::HighestTier => write!(f, "tier_highest"),
+            CoreTarget::LowestTier => write!(f, "tier_lowest"),
+            CoreTarget::Tier(class) => write!(f, "tier{}", class),
+            CoreTarget::Named(name) => write!(f, "{}", name),
+            CoreTarget::Cpus(cpus) => write!(f, "{}", cpus),
+            CoreTarget::Physical(inner) => write!(f, "{}_physical", inner),
+        }
+    }
+}
+
+impl Serialize for CoreTarget {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for CoreTarget {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
     }
 }
 
@@ -90,25 +475,168 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_core_mask_calculation() {
-        let p_cores = vec![0, 1, 2, 3];
-        let e_cores = vec![4, 5, 6, 7];
+    fn test_core_set_calculation() {
+        let p_cores = CpuSet::from_cpus(0..4);
+        let e_cores = CpuSet::from_cpus(4..8);
         let info = CoreInfo::new(p_cores, e_cores);
 
         // P-cores: 0b00001111 = 0x0F
-        assert_eq!(info.p_core_mask, 0x0F);
+        assert_eq!(info.p_cores.group_mask(0), 0x0F);
         // E-cores: 0b11110000 = 0xF0
-        assert_eq!(info.e_core_mask, 0xF0);
+        assert_eq!(info.e_cores.group_mask(0), 0xF0);
         assert_eq!(info.total_cores, 8);
         assert!(info.has_hybrid_architecture());
     }
 
     #[test]
     fn test_all_cores() {
-        let info = CoreInfo::all_cores(8);
+        let info = CoreInfo::all_cores(CpuSet::from_cpus(0..8));
         assert_eq!(info.total_cores, 8);
-        assert_eq!(info.p_core_mask, 0xFF);
-        assert_eq!(info.e_core_mask, 0);
+        assert_eq!(info.p_cores.group_mask(0), 0xFF);
+        assert!(info.e_cores.is_empty());
         assert!(!info.has_hybrid_architecture());
     }
+
+    #[test]
+    fn test_more_than_64_cores() {
+        // Dual-socket machine with two processor groups of 48 threads each
+        let group0 = CpuSet::from_group_mask(0, (1 << 48) - 1);
+        let group1 = CpuSet::from_group_mask(1, (1 << 48) - 1);
+        let info = CoreInfo::all_cores(group0.union(&group1));
+
+        assert_eq!(info.total_cores, 96);
+        assert_eq!(info.p_cores.group_count(), 2);
+        assert!(info.p_cores.contains(64 + 47));
+        assert!(!info.p_cores.contains(48));
+    }
+
+    #[test]
+    fn test_three_tiers() {
+        // Meteor Lake: P-cores, E-cores and low-power E-cores on the SoC tile
+        let mut classes = BTreeMap::new();
+        classes.insert(0, CpuSet::from_cpus(20..22));
+        classes.insert(1, CpuSet::from_cpus(12..20));
+        classes.insert(2, CpuSet::from_cpus(0..12));
+        let info = CoreInfo::from_efficiency_classes(classes);
+
+        assert_eq!(info.total_cores, 22);
+        assert_eq!(info.p_cores, CpuSet::from_cpus(0..12));
+        assert_eq!(info.e_cores, CpuSet::from_cpus(12..22));
+        assert_eq!(info.highest_tier(), info.p_cores);
+        assert_eq!(info.lowest_tier(), CpuSet::from_cpus(20..22));
+        assert_eq!(info.efficiency_class(15), Some(1));
+        assert_eq!(info.resolve(&CoreTarget::Tier(1)), CpuSet::from_cpus(12..20));
+        assert!(info.resolve(&CoreTarget::Tier(3)).is_empty());
+        assert!(info.to_string().contains("tiers: [2: 0-11; 1: 12-19; 0: 20-21]"));
+    }
+
+    #[test]
+    fn test_highest_class_is_not_one() {
+        let mut classes = BTreeMap::new();
+        classes.insert(0, CpuSet::from_cpus(4..8));
+        classes.insert(2, CpuSet::from_cpus(0..4));
+        let info = CoreInfo::from_efficiency_classes(classes);
+
+        assert_eq!(info.p_cores, CpuSet::from_cpus(0..4));
+        assert_eq!(info.resolve(&CoreTarget::LowestTier), CpuSet::from_cpus(4..8));
+    }
+
+    #[test]
+    fn test_l3_named_sets() {
+        // 7950X3D: CCD0 has 96 MB of L3 with V-Cache, CCD1 32 MB
+        let ccd0 = CpuSet::from_cpus((0..8).chain(16..24));
+        let ccd1 = CpuSet::from_cpus((8..16).chain(24..32));
+        let info = CoreInfo::all_cores(ccd0.union(&ccd1)).with_l3_caches(vec![
+            CacheGroup { cpus: ccd1.clone(), size: 32 << 20 },
+            CacheGroup { cpus: ccd0.clone(), size: 96 << 20 },
+        ]);
+
+        assert_eq!(info.named_set("ccd0"), Some(&ccd0));
+        assert_eq!(info.named_set("ccd1"), Some(&ccd1));
+        assert_eq!(info.resolve(&CoreTarget::Named("l3_largest".to_string())), ccd0);
+        assert!(info.resolve(&CoreTarget::Named("ccd2".to_string())).is_empty());
+        assert!(info.can_resolve(&"ccd1_physical".parse().unwrap()));
+        assert!(!info.can_resolve(&"ccd2".parse().unwrap()));
+        assert!(!info.can_resolve(&"numa0".parse().unwrap()));
+        assert!(info.can_resolve(&CoreTarget::PCores));
+        assert!(!info.can_resolve(&CoreTarget::ECores));
+        assert!(info.to_string().contains("L3: [0-7,16-23 (96 MB); 8-15,24-31 (32 MB)]"));
+    }
+
+    #[test]
+    fn test_uniform_l3_largest_is_every_cache() {
+        let info = CoreInfo::all_cores(CpuSet::from_cpus(0..16)).with_l3_caches(vec![
+            CacheGroup { cpus: CpuSet::from_cpus(0..8), size: 32 << 20 },
+            CacheGroup { cpus: CpuSet::from_cpus(8..16), size: 32 << 20 },
+        ]);
+        assert_eq!(info.named_set("l3_largest"), Some(&CpuSet::from_cpus(0..16)));
+    }
+
+    #[test]
+    fn test_numa_nodes() {
+        // Two sockets of 4 cores with Hyper-Threading, Linux numbering
+        let node0 = CpuSet::from_cpus((0..4).chain(8..12));
+        let node1 = CpuSet::from_cpus((4..8).chain(12..16));
+        let info = CoreInfo::all_cores(CpuSet::from_cpus(0..16)).with_numa_nodes(vec![
+            NumaNode { node: 1, cpus: node1.clone() },
+            NumaNode { node: 0, cpus: node0.clone() },
+        ]);
+
+        assert_eq!(info.resolve(&CoreTarget::Named("numa1".to_string())), node1);
+        assert!(info.to_string().contains("NUMA: [0: 0-3,8-11; 1: 4-7,12-15]"));
+
+        // The node with the larger share wins; ties are split by PID
+        let mostly_node1 = CpuSet::from_cpus([3, 4, 5, 6]);
+        assert_eq!(info.confine_to_numa_node(&mostly_node1, 7), CpuSet::from_cpus([4, 5, 6]));
+        let all = info.all();
+        assert_eq!(info.confine_to_numa_node(&all, 10), node0);
+        assert_eq!(info.confine_to_numa_node(&all, 11), node1);
+        assert_eq!(info.confine_to_numa_node(&node0, 11), node0);
+    }
+
+    #[test]
+    fn test_physical_core_targets() {
+        // 2 P-cores with Hyper-Threading (Windows numbering) and 2 E-cores
+        let info = CoreInfo::new(CpuSet::from_cpus(0..4), CpuSet::from_cpus(4..6)).with_physical_cores(vec![
+            CpuSet::from_cpus([2, 3]),
+            CpuSet::from_cpus([0, 1]),
+            CpuSet::from_cpus([4]),
+            CpuSet::from_cpus([5]),
+        ]);
+
+        let p_physical: CoreTarget = "p_cores_physical".parse().unwrap();
+        assert_eq!(p_physical, CoreTarget::Physical(Box::new(CoreTarget::PCores)));
+        assert_eq!(info.resolve(&p_physical), CpuSet::from_cpus([0, 2]));
+        assert_eq!(info.resolve(&"e_cores_physical".parse().unwrap()), CpuSet::from_cpus([4, 5]));
+        assert_eq!(info.resolve(&"all_cores_physical".parse().unwrap()), CpuSet::from_cpus([0, 2, 4, 5]));
+
+        // Only the threads in the target count, and unknown layouts are kept
+        assert_eq!(info.first_threads(&CpuSet::from_cpus([1, 3])), CpuSet::from_cpus([1, 3]));
+        assert_eq!(CoreInfo::all_cores(CpuSet::from_cpus(0..4)).first_threads(&CpuSet::from_cpus(0..4)).len(), 4);
+        assert!(info.to_string().contains("physical cores: 4"));
+    }
+
+    #[test]
+    fn test_core_target_parsing() {
+        assert_eq!("p_cores".parse::<CoreTarget>().unwrap(), CoreTarget::PCores);
+        assert_eq!("Tier_Lowest".parse::<CoreTarget>().unwrap(), CoreTarget::LowestTier);
+        assert_eq!("tier2".parse::<CoreTarget>().unwrap(), CoreTarget::Tier(2));
+        assert_eq!(
+            "0-3".parse::<CoreTarget>().unwrap(),
+            CoreTarget::Cpus(CpuSet::from_cpus(0..4))
+        );
+        assert_eq!("CCD1".parse::<CoreTarget>().unwrap(), CoreTarget::Named("ccd1".to_string()));
+        assert!("tierx".parse::<CoreTarget>().is_err());
+        assert!("fast-cores".parse::<CoreTarget>().is_err());
+        assert!("p_core".parse::<CoreTarget>().is_err());
+        assert!("ccd".parse::<CoreTarget>().is_err());
+        assert!("numa_1".parse::<CoreTarget>().is_err());
+        assert!("0-3,x".parse::<CoreTarget>().is_err());
+        assert!("0-3_physical".parse::<CoreTarget>().is_err());
+        assert!("p_cores_physical_physical".parse::<CoreTarget>().is_err());
+
+        for name in ["e_cores", "all_cores", "tier_highest", "tier0", "l3_largest", "numa1", "ccd0_physical", "0-3,8"] {
+            assert_eq!(name.parse::<CoreTarget>().unwrap().to_string(), name);
+        }
+    }
 }
@@ -0,0 +1,207 @@
+use crate::cpu::backend::AffinityBackend;
+use crate::cpu::cpuset::CpuSet;
+use crate::utils::ServiceError;
+use windows::Win32::Foundation::{
+    CloseHandle, GetLastError, ERROR_NOT_ENOUGH_MEMORY, ERROR_NOT_ENOUGH_QUOTA, ERROR_NO_SYSTEM_RESOURCES,
+    ERROR_OUTOFMEMORY, HANDLE,
+};
+use windows::Win32::System::SystemInformation::GROUP_AFFINITY;
+use windows::Win32::System::Threading::{
+    GetActiveProcessorCount, GetProcessAffinityMask, GetProcessGroupAffinity, OpenProcess,
+    SetProcessAffinityMask, SetProcessDefaultCpuSetMasks, PROCESS_ACCESS_RIGHTS,
+    PROCESS_QUERY_INFORMATION, PROCESS_SET_INFORMATION,
+};
+
+/// Affinity backend using `SetProcessAffinityMask`
+///
+/// A process affinity mask only covers the process's own processor group.
+/// Sets in another group, or spanning several groups, are applied as default
+/// CPU sets with `SetProcessDefaultCpuSetMasks` (Windows 11 / Server 2022).
+/// A default CPU set is a soft placement, not a hard affinity: threads that
+/// set their own affinity or CPU sets ignore it, and the scheduler may run
+/// threads elsewhere when the set is busy.
+pub struct WindowsAffinityBackend;
+
+impl WindowsAffinityBackend {
+    fn open_process(pid: u32) -> Result<HANDLE, ServiceError> {
+        let access_rights = PROCESS_ACCESS_RIGHTS(
+            PROCESS_SET_INFORMATION.0 | PROCESS_QUERY_INFORMATION.0
+        );
+
+        let handle = unsafe {
+            OpenProcess(access_rights, false, pid)
+        };
+
+        match handle {
+            Ok(h) if !h.is_invalid() => Ok(h),
+            _ => Err(ServiceError::AffinitySetting(format!(
+                "Failed to open process with PID: {} (insufficient permissions or process doesn't exist)",
+                pid
+            ))),
+        }
+    }
+
+    /// Processor groups the process currently has threads in
+    fn process_groups(handle: HANDLE) -> Vec<u16> {
+        let mut groups = [0u16; 64];
+        let mut count = groups.len() as u16;
+        let result = unsafe {
+            GetProcessGroupAffinity(handle, &mut count, groups.as_mut_ptr())
+        };
+
+        if result.as_bool() {
+            groups[..count as usize].to_vec()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn query_affinity(handle: HANDLE, pid: u32) -> Result<CpuSet, ServiceError> {
+        let groups = Self::process_groups(handle);
+
+        // A multi-group process has no single affinity mask; report every
+        // active processor of the groups it runs in
+        if groups.len() > 1 {
+            let mut cpus = CpuSet::new();
+            for group in groups {
+                let count = unsafe { GetActiveProcessorCount(group) };
+                cpus.set_group_mask(group, Self::low_bits(count));
+            }
+            return Ok(cpus);
+        }
+
+        let mut process_mask = 0usize;
+        let mut system_mask = 0usize;
+        let result = unsafe {
+            GetProcessAffinityMask(handle, &mut process_mask, &mut system_mask)
+        };
+
+        if !result.as_bool() {
+            return Err(ServiceError::AffinitySetting(format!(
+                "Failed to get affinity mask for PID: {}",
+                pid
+            )));
+        }
+
+        let group = groups.first().copied().unwrap_or(0);
+        Ok(CpuSet::from_group_mask(group, process_mask as u64))
+    }
+
+    fn apply_affinity(handle: HANDLE, pid: u32, cpus: &CpuSet) -> Result<(), ServiceError> {
+        let process_groups = Self::process_groups(handle);
+        let mut target_groups = cpus.group_masks();
+
+        // Fast path: the whole set lies in the group the process runs in
+        if let (Some((group, mask)), None) = (target_groups.next(), target_groups.next()) {
+            if process_groups.len() <= 1 && process_groups.first().copied().unwrap_or(0) == group {
+                let result = unsafe {
+                    SetProcessAffinityMask(handle, mask as usize)
+                };
+                if !result.as_bool() {
+                    return Err(Self::set_error(format!(
+                        "Failed to set affinity mask 0x{:X} in group {} for PID: {}",
+                        mask, group, pid
+                    )));
+                }
+                return Ok(());
+            }
+        }
+
+        let masks: Vec<GROUP_AFFINITY> = cpus
+            .group_masks()
+            .map(|(group, mask)| GROUP_AFFINITY {
+                Mask: mask as usize,
+                Group: group,
+                Reserved: [0; 3],
+            })
+            .collect();
+
+        let result = unsafe {
+            SetProcessDefaultCpuSetMasks(handle, Some(&masks))
+        };
+        if !result.as_bool() {
+            return Err(Self::set_error(format!(
+                "Failed to set default CPU sets {} for PID: {}",
+                cpus, pid
+            )));
+        }
+
+        log::info!(
+            "CPUs {} span processor groups; applied as a default CPU set, a soft placement that \
+             threads with their own affinity ignore (PID: {})",
+            cpus, pid
+        );
+        Ok(())
+    }
+
+    /// Error for a failed set call; transient if Windows ran short of memory
+    /// or quota, which can clear up on a retry
+    fn set_error(message: String) -> ServiceError {
+        let code = unsafe { GetLastError() };
+        if matches!(
+            code,
+            ERROR_NOT_ENOUGH_MEMORY | ERROR_OUTOFMEMORY | ERROR_NO_SYSTEM_RESOURCES | ERROR_NOT_ENOUGH_QUOTA
+        ) {
+            ServiceError::TransientAffinity(message)
+        } else {
+            ServiceError::AffinitySetting(message)
+        }
+    }
+
+    fn low_bits(count: u32) -> u64 {
+        if count >= 64 {
+            u64::MAX
+        } else {
+            (1u64 << count) - 1
+        }
+    }
+}
+
+impl AffinityBackend for WindowsAffinityBackend {
+    fn get_affinity(&self, pid: u32) -> Result<CpuSet, ServiceError> {
+        let handle = Self::open_process(pid)?;
+        let result = Self::query_affinity(handle, pid);
+
+        unsafe {
+            let _ = CloseHandle(handle);
+        }
+
+        result
+    }
+
+    fn set_affinity(&self, pid: u32, cpus: &CpuSet) -> Result<(), ServiceError> {
+        if cpus.is_empty() {
+            return Err(ServiceError::AffinitySetting(format!(
+                "Refusing to set an empty CPU set for PID: {}",
+                pid
+            )));
+        }
+
+        let handle = Self::open_process(pid)?;
+        let result = Self::apply_affinity(handle, pid, cpus);
+
+        // Close handle
+        unsafe {
+            let _ = CloseHandle(handle);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_own_affinity() {
+        let cpus = WindowsAffinityBackend.get_affinity(std::process::id()).unwrap();
+        assert!(!cpus.is_empty());
+    }
+
+    #[test]
+    fn test_low_bits() {
+        assert_eq!(WindowsAffinityBackend::low_bits(4), 0xF);
+        assert_eq!(WindowsAffinityBackend::low_bits(64), u64::MAX);
+    }
+}
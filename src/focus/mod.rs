@@ -0,0 +1,7 @@
+pub mod provider;
+#[cfg(windows)]
+pub mod windows;
+
+pub use provider::{default_focus_provider, FocusProvider, NoFocusProvider};
+#[cfg(windows)]
+pub use windows::WindowsFocusProvider;
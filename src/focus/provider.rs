@@ -0,0 +1,39 @@
+/// A platform mechanism for finding the application the user is working in
+pub trait FocusProvider: Send + Sync {
+    /// PID of the process owning the foreground window, if there is one
+    fn foreground_pid(&self) -> Option<u32>;
+
+    /// Why the provider can never report a foreground window in this
+    /// process, if it cannot; checked once at startup
+    fn unavailable(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Provider for platforms without a notion of a foreground window; never
+/// reports one, so nothing is boosted
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoFocusProvider;
+
+impl FocusProvider for NoFocusProvider {
+    fn foreground_pid(&self) -> Option<u32> {
+        None
+    }
+
+    fn unavailable(&self) -> Option<String> {
+        Some("this platform has no focus provider".to_string())
+    }
+}
+
+/// Get the focus provider for the current platform
+pub fn default_focus_provider() -> Box<dyn FocusProvider> {
+    #[cfg(windows)]
+    {
+        Box::new(crate::focus::WindowsFocusProvider)
+    }
+
+    #[cfg(not(windows))]
+    {
+        Box::new(NoFocusProvider)
+    }
+}
@@ -0,0 +1,32 @@
+use crate::focus::provider::FocusProvider;
+use windows::Win32::System::RemoteDesktop::ProcessIdToSessionId;
+use windows::Win32::System::Threading::GetCurrentProcessId;
+use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+/// Focus provider using `GetForegroundWindow`
+///
+/// Only sees the desktop of the session the process runs in, so a service in
+/// session 0 never finds a foreground window.
+pub struct WindowsFocusProvider;
+
+impl FocusProvider for WindowsFocusProvider {
+    fn foreground_pid(&self) -> Option<u32> {
+        let window = unsafe { GetForegroundWindow() };
+        if window.0 == 0 {
+            return None;
+        }
+
+        let mut pid = 0u32;
+        unsafe { GetWindowThreadProcessId(window, Some(&mut pid)) };
+        (pid != 0).then_some(pid)
+    }
+
+    fn unavailable(&self) -> Option<String> {
+        let mut session = 0u32;
+        let found = unsafe { ProcessIdToSessionId(GetCurrentProcessId(), &mut session) }.as_bool();
+        (found && session == 0).then(|| {
+            "running in session 0, which has no foreground window; run in CLI mode in the user's session instead"
+                .to_string()
+        })
+    }
+}
@@ -1,11 +1,19 @@
 pub mod config;
 pub mod cpu;
+pub mod focus;
+pub mod power;
 pub mod process;
 pub mod service;
+pub mod sim;
 pub mod utils;
 
 pub use config::{Config, ConfigLoader};
-pub use cpu::{AffinityManager, CoreInfo, CpuDetector, DetectionMode};
-pub use process::{ProcessCache, ProcessManager, ProcessMonitor};
+pub use cpu::{AffinityBackend, AffinityManager, CoreInfo, CoreTarget, CpuDetector, CpuSet, DetectionMode};
+#[cfg(windows)]
+pub use process::ProcessMonitor;
+pub use focus::FocusProvider;
+pub use power::{PowerSource, PowerState};
+pub use process::{ProcFsSource, ProcessCache, ProcessInfo, ProcessManager, ProcessSource};
 pub use service::ServiceRunner;
+pub use sim::{SimulatedClock, SimulatedSystem};
 pub use utils::ServiceError;
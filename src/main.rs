@@ -1,3 +1,4 @@
+use process_cpu_auto::process::{ProcessOwner, ProcessView};
 use process_cpu_auto::{service, ServiceRunner, ServiceError};
 use std::env;
 
@@ -10,26 +11,73 @@ fn main() -> Result<(), ServiceError> {
         return service::run_service();
     }
 
+    // Parse command line arguments
+    let explicit_config = args.get(1).map(String::as_str).filter(|arg| !arg.starts_with("--"));
+    let config_path = explicit_config.unwrap_or("config.toml");
+
+    // Capture the raw processor topology for a test fixture
+    if let Some(dump_path) = flag_value(&args, "--dump-topology") {
+        return dump_topology(dump_path);
+    }
+
+    // Explain which rule applies to a process name, optionally with its path,
+    // command line, owner and session
+    if let Some(process_name) = flag_value(&args, "--explain") {
+        let mut process = ProcessView::from_name(process_name);
+        if let Some(exe_path) = flag_value(&args, "--exe-path") {
+            process = process.with_exe_path(exe_path);
+        }
+        if let Some(cmdline) = flag_value(&args, "--cmdline") {
+            process = process.with_cmdline(cmdline);
+        }
+        if let Some(user) = flag_value(&args, "--user") {
+            process = process.with_owner(ProcessOwner {
+                name: Some(user.to_string()),
+                uid: user.parse().ok(),
+            });
+        }
+        if let Some(session) = flag_value(&args, "--session") {
+            let session = session.parse().map_err(|_| {
+                ServiceError::Config(format!("Invalid session ID: {}", session))
+            })?;
+            process = process.with_session(session);
+        }
+        println!("{}", ServiceRunner::explain(config_path, &process)?);
+        return Ok(());
+    }
+
+    // Replaying a trace only simulates affinity changes, so no privileges are needed.
+    // Without a config path the configuration recorded in the trace is used.
+    if let Some(trace_path) = flag_value(&args, "--replay") {
+        let mut runner = ServiceRunner::replay(explicit_config, trace_path)?;
+        return runner.run();
+    }
+
     // Running in CLI mode
     println!("Windows Process CPU Affinity Auto Service");
     println!("==========================================");
     println!();
 
     // Check administrator privileges first
-    process_cpu_auto::utils::privilege::require_administrator()?;
+    #[cfg(windows)]
+    {
+        process_cpu_auto::utils::privilege::require_administrator()?;
 
-    println!("✓ Running with Administrator privileges");
-    println!();
+        println!("✓ Running with Administrator privileges");
+        println!();
+    }
 
-    // Parse command line arguments
-    let config_path = if args.len() > 1 && !args[1].starts_with("--") {
-        &args[1]
-    } else {
-        "config.toml"
-    };
+    #[cfg(not(windows))]
+    if !process_cpu_auto::utils::is_elevated() {
+        println!("! Not running as root: only processes owned by the current user can be pinned");
+        println!();
+    }
 
     // Create and run service in CLI mode
-    let mut runner = ServiceRunner::new(config_path)?;
+    let mut runner = match flag_value(&args, "--record") {
+        Some(trace_path) => ServiceRunner::recording(config_path, trace_path)?,
+        None => ServiceRunner::new(config_path)?,
+    };
 
     // Run the service
     match runner.run() {
@@ -43,3 +91,26 @@ fn main() -> Result<(), ServiceError> {
         }
     }
 }
+
+#[cfg(windows)]
+fn dump_topology(path: &str) -> Result<(), ServiceError> {
+    let buffer = process_cpu_auto::CpuDetector::dump_topology()?;
+    std::fs::write(path, &buffer)?;
+    println!("Wrote {} bytes of processor topology to {}", buffer.len(), path);
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn dump_topology(_path: &str) -> Result<(), ServiceError> {
+    Err(ServiceError::CpuDetection(
+        "--dump-topology captures the Windows GetLogicalProcessorInformationEx buffer and is only available on Windows".to_string(),
+    ))
+}
+
+/// Get the value following a `--flag value` pair
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .map(String::as_str)
+}
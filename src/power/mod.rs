@@ -0,0 +1,9 @@
+pub mod source;
+pub mod sysfs;
+#[cfg(windows)]
+pub mod windows;
+
+pub use source::{default_power_source, PowerSource, PowerState};
+pub use sysfs::SysfsPowerSource;
+#[cfg(windows)]
+pub use windows::WindowsPowerSource;
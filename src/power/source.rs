@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Where the machine is drawing power from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerState {
+    /// Mains power, also assumed when the state is unknown
+    #[default]
+    Ac,
+    Battery,
+}
+
+impl fmt::Display for PowerState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PowerState::Ac => write!(f, "AC"),
+            PowerState::Battery => write!(f, "battery"),
+        }
+    }
+}
+
+/// A platform mechanism for reading the power source
+pub trait PowerSource: Send + Sync {
+    /// The current power state, or `None` if it cannot be told
+    fn power_state(&self) -> Option<PowerState>;
+}
+
+/// Get the power source for the current platform
+pub fn default_power_source() -> Box<dyn PowerSource> {
+    #[cfg(windows)]
+    {
+        Box::new(crate::power::WindowsPowerSource)
+    }
+
+    #[cfg(not(windows))]
+    {
+        Box::new(crate::power::SysfsPowerSource::new())
+    }
+}
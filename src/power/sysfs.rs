@@ -0,0 +1,124 @@
+use crate::power::source::{PowerSource, PowerState};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Power source reading `class/power_supply` from Linux sysfs
+///
+/// Any online mains or USB supply means AC. Without one, a system battery
+/// that is discharging means battery power. Peripheral batteries (`scope`
+/// of `Device`, such as a wireless mouse) are ignored.
+pub struct SysfsPowerSource {
+    root: PathBuf,
+}
+
+impl SysfsPowerSource {
+    pub fn new() -> Self {
+        Self::with_root("/sys")
+    }
+
+    /// Read supplies from an alternative sysfs root, such as a test fixture
+    pub fn with_root<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    fn read(supply: &Path, attribute: &str) -> Option<String> {
+        fs::read_to_string(supply.join(attribute))
+            .ok()
+            .map(|value| value.trim().to_string())
+    }
+}
+
+impl Default for SysfsPowerSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PowerSource for SysfsPowerSource {
+    fn power_state(&self) -> Option<PowerState> {
+        let entries = fs::read_dir(self.root.join("class/power_supply")).ok()?;
+
+        let mut adapters = 0;
+        let mut discharging = None;
+        for entry in entries.flatten() {
+            let supply = entry.path();
+            if Self::read(&supply, "scope").as_deref() == Some("Device") {
+                continue;
+            }
+
+            match Self::read(&supply, "type").as_deref() {
+                Some("Battery") => {
+                    let status = Self::read(&supply, "status");
+                    let is_discharging = status.as_deref() == Some("Discharging");
+                    discharging = Some(discharging.unwrap_or(false) || is_discharging);
+                }
+                Some(_) => {
+                    if Self::read(&supply, "online").as_deref() == Some("1") {
+                        return Some(PowerState::Ac);
+                    }
+                    adapters += 1;
+                }
+                None => {}
+            }
+        }
+
+        match discharging {
+            // Every adapter is offline, or the battery says it is draining
+            Some(discharging) if adapters > 0 || discharging => Some(PowerState::Battery),
+            Some(_) => Some(PowerState::Ac),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_supply(root: &Path, name: &str, attributes: &[(&str, &str)]) {
+        let supply = root.join("class/power_supply").join(name);
+        fs::create_dir_all(&supply).unwrap();
+        for (attribute, value) in attributes {
+            fs::write(supply.join(attribute), format!("{}\n", value)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_laptop() {
+        let root = tempfile::tempdir().unwrap();
+        write_supply(root.path(), "AC", &[("type", "Mains"), ("online", "1")]);
+        write_supply(root.path(), "BAT0", &[("type", "Battery"), ("status", "Charging")]);
+        write_supply(root.path(), "hidpp_battery_0", &[("type", "Battery"), ("scope", "Device"), ("status", "Discharging")]);
+
+        let source = SysfsPowerSource::with_root(root.path());
+        assert_eq!(source.power_state(), Some(PowerState::Ac));
+
+        write_supply(root.path(), "AC", &[("online", "0")]);
+        write_supply(root.path(), "BAT0", &[("status", "Discharging")]);
+        assert_eq!(source.power_state(), Some(PowerState::Battery));
+
+        // A full battery on an offline adapter is still battery power
+        write_supply(root.path(), "BAT0", &[("status", "Full")]);
+        assert_eq!(source.power_state(), Some(PowerState::Battery));
+    }
+
+    #[test]
+    fn test_battery_only_and_desktops() {
+        let root = tempfile::tempdir().unwrap();
+        let source = SysfsPowerSource::with_root(root.path());
+        // No power_supply class at all
+        assert_eq!(source.power_state(), None);
+
+        // Only a peripheral battery, as on a desktop
+        write_supply(root.path(), "hidpp_battery_0", &[("type", "Battery"), ("scope", "Device"), ("status", "Discharging")]);
+        assert_eq!(source.power_state(), None);
+
+        // A battery without an adapter entry, as on some tablets
+        write_supply(root.path(), "BAT1", &[("type", "Battery"), ("status", "Discharging")]);
+        assert_eq!(source.power_state(), Some(PowerState::Battery));
+        write_supply(root.path(), "BAT1", &[("status", "Charging")]);
+        assert_eq!(source.power_state(), Some(PowerState::Ac));
+    }
+}
@@ -0,0 +1,21 @@
+use crate::power::source::{PowerSource, PowerState};
+use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+/// Power source using `GetSystemPowerStatus`
+pub struct WindowsPowerSource;
+
+impl PowerSource for WindowsPowerSource {
+    fn power_state(&self) -> Option<PowerState> {
+        let mut status = SYSTEM_POWER_STATUS::default();
+        if !unsafe { GetSystemPowerStatus(&mut status) }.as_bool() {
+            return None;
+        }
+
+        match status.ACLineStatus {
+            0 => Some(PowerState::Battery),
+            1 => Some(PowerState::Ac),
+            // 255: unknown
+            _ => None,
+        }
+    }
+}
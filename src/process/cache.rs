@@ -60,6 +60,14 @@ impl ProcessCache {
         }
     }
 
+    /// Mark a process as needing another look on the next scan
+    pub fn unmark_processed(&mut self, pid: u32) {
+        self.processed_pids.remove(&pid);
+        if let Some(entry) = self.processes.get_mut(&pid) {
+            entry.processed = false;
+        }
+    }
+
     /// Clean up stale process entries
     pub fn cleanup(&mut self) -> usize {
         let now = Instant::now();
@@ -83,6 +91,21 @@ impl ProcessCache {
         removed
     }
 
+    /// Remove entries for processes that are no longer running
+    pub fn remove_exited(&mut self, live_pids: &HashSet<u32>) -> usize {
+        let initial_count = self.processes.len();
+
+        self.processes.retain(|pid, _| live_pids.contains(pid));
+        self.processed_pids.retain(|pid| live_pids.contains(pid));
+
+        let removed = initial_count - self.processes.len();
+        if removed > 0 {
+            log::debug!("Removed {} exited processes from cache", removed);
+        }
+
+        removed
+    }
+
     /// Get the number of processes in cache
     pub fn len(&self) -> usize {
         self.processes.len()
@@ -149,6 +172,21 @@ mod tests {
         assert!(!cache.is_processed(1234));
     }
 
+    #[test]
+    fn test_remove_exited() {
+        let mut cache = ProcessCache::new(300);
+
+        cache.mark_seen(1234, "test1.exe".to_string());
+        cache.mark_seen(5678, "test2.exe".to_string());
+        cache.mark_processed(5678);
+
+        let live: HashSet<u32> = [1234].into_iter().collect();
+        assert_eq!(cache.remove_exited(&live), 1);
+        assert!(!cache.is_new_process(1234));
+        assert!(cache.is_new_process(5678));
+        assert!(!cache.is_processed(5678));
+    }
+
     #[test]
     fn test_cache_stats() {
         let mut cache = ProcessCache::new(300);
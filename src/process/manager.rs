@@ -1,36 +1,163 @@
-use crate::config::settings::{Config, MatchMode};
-use crate::cpu::AffinityManager;
-use crate::process::{ProcessCache, ProcessMonitor};
-use crate::utils::ServiceError;
+use crate::config::settings::Config;
+use crate::cpu::{AffinityManager, CoreTarget, CpuSet};
+use crate::focus::{default_focus_provider, FocusProvider};
+use crate::power::{default_power_source, PowerSource};
+use crate::process::rules::{MatchDecision, MatchOutcome, RuleSet};
+use crate::process::{
+    default_source, CpuSamples, ProcessCache, ProcessInfo, ProcessSource, ProcessTree, ProcessView,
+};
+use crate::utils::{Clock, ServiceError, SystemClock};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 pub struct ProcessManager {
     config: Arc<Config>,
     affinity_manager: Arc<AffinityManager>,
+    source: Box<dyn ProcessSource>,
     cache: ProcessCache,
-    match_mode: MatchMode,
+    /// `[[rules]]` and the whitelist in match order, with the exclude list
+    rules: RuleSet,
+    /// PIDs present in the most recent snapshot
+    live_pids: HashSet<u32>,
+    /// Time source for rule schedules and CPU usage windows
+    clock: Box<dyn Clock>,
+    /// Picks between AC and battery targets
+    power: Box<dyn PowerSource>,
+    /// Reports the foreground process for `boost_on_focus`
+    focus: Box<dyn FocusProvider>,
+    /// The process currently moved to its rule's `focus_target`
+    boosted: Option<u32>,
+    /// Pinned processes by PID, so they can be moved or reverted when a
+    /// schedule window opens or closes or the power source changes
+    applied: HashMap<u32, AppliedAffinity>,
+    /// CPU time readings of pinned processes whose rule has `idle_demotion`
+    usage: HashMap<u32, CpuSamples>,
+}
+
+/// The rule and target a process was pinned by and its affinity before that
+struct AppliedAffinity {
+    rule_index: usize,
+    target: CoreTarget,
+    /// `None` if it could not be read
+    original: Option<CpuSet>,
+    /// Moved to the rule's idle target for using too little CPU
+    demoted: bool,
 }
 
 impl ProcessManager {
-    pub fn new(config: Arc<Config>, affinity_manager: Arc<AffinityManager>) -> Self {
-        let match_mode = MatchMode::from_str(&config.whitelist.match_mode);
+    pub fn new(config: Arc<Config>, affinity_manager: Arc<AffinityManager>) -> Result<Self, ServiceError> {
+        Self::with_source(config, affinity_manager, default_source())
+    }
+
+    /// Create a process manager that enumerates processes through the given source
+    pub fn with_source(
+        config: Arc<Config>,
+        affinity_manager: Arc<AffinityManager>,
+        source: Box<dyn ProcessSource>,
+    ) -> Result<Self, ServiceError> {
+        let rules = RuleSet::from_config(&config)?;
+        rules.check_targets(&affinity_manager.get_core_info())?;
         let cache = ProcessCache::new(config.advanced.cache_cleanup_interval_secs);
 
-        Self {
+        Ok(Self {
             config,
             affinity_manager,
+            source,
             cache,
-            match_mode,
+            rules,
+            live_pids: HashSet::new(),
+            clock: Box::new(SystemClock),
+            power: default_power_source(),
+            focus: default_focus_provider(),
+            boosted: None,
+            applied: HashMap::new(),
+            usage: HashMap::new(),
+        })
+    }
+
+    /// The rules built from the configuration, in match order
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    /// Use the given focus provider instead of the platform one
+    pub fn with_focus_provider(mut self, focus: Box<dyn FocusProvider>) -> Self {
+        self.focus = focus;
+        self
+    }
+
+    /// A warning for the startup log if some rule has `boost_on_focus` but
+    /// the focus provider can never report a foreground window
+    pub fn focus_warning(&self) -> Option<String> {
+        let rules: Vec<&str> = self
+            .rules
+            .rules()
+            .iter()
+            .filter(|rule| rule.boost_on_focus)
+            .map(|rule| rule.name.as_str())
+            .collect();
+        if rules.is_empty() {
+            return None;
         }
+
+        let reason = self.focus.unavailable()?;
+        Some(format!(
+            "boost_on_focus of rule {} will never boost anything: {}",
+            rules.join(", "),
+            reason
+        ))
+    }
+
+    /// Use the given power source instead of the platform one
+    pub fn with_power_source(mut self, power: Box<dyn PowerSource>) -> Self {
+        self.power = power;
+        self
+    }
+
+    /// Use the given clock for rule schedules and CPU usage windows instead
+    /// of the system clock
+    pub fn with_clock(mut self, clock: Box<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
     }
 
     pub fn scan_and_process(&mut self) -> Result<usize, ServiceError> {
         // Get all running processes
-        let processes = ProcessMonitor::get_all_processes()?;
+        let processes = self.source.snapshot()?;
+        self.live_pids = processes.iter().map(|p| p.pid).collect();
 
+        let tree = ProcessTree::from_snapshot(&processes);
+        // Outcomes by PID, so ancestors checked for inheritance are evaluated once
+        let mut outcomes = HashMap::new();
         let mut processed_count = 0;
+        // Pinned to their rule's target during this scan, undoing any boost
+        let mut pinned = HashSet::new();
 
-        for process in processes {
+        let changed = self.rules.update_schedules(self.clock.now());
+        for &rule_index in &changed {
+            if let Some(rule) = self.rules.get(rule_index) {
+                let state = if self.rules.is_active(rule_index) { "opened" } else { "closed" };
+                log::info!("Rule {}: schedule window {}", rule.name, state);
+            }
+        }
+
+        // Unknown counts as AC, so desktops keep the plain targets; the
+        // power source is only asked if some rule has AC or battery targets
+        let mut power_changed = false;
+        if self.rules.has_power_targets() {
+            let power_state = self.power.power_state().unwrap_or_default();
+            power_changed = self.rules.set_power_state(power_state);
+            if power_changed {
+                log::info!("Power source changed to {}", power_state);
+            }
+        }
+        let power_state = self.rules.power_state();
+
+        if !changed.is_empty() || power_changed {
+            self.reapply_pinned(&tree, &mut outcomes);
+        }
+
+        for process in &processes {
             // Skip if already processed
             if self.cache.is_processed(process.pid) {
                 continue;
@@ -42,27 +169,65 @@ impl ProcessManager {
                 self.cache.mark_seen(process.pid, process.name.clone());
             }
 
-            // Check if process matches whitelist
-            if !self.is_whitelisted(&process.name) {
-                continue;
-            }
-
-            // Check if process is excluded
-            if self.is_excluded(&process.name) {
-                log::debug!("Process {} is in exclude list, skipping", process.name);
-                continue;
-            }
+            // Find the first rule matching the process, or else one inherited
+            // from the nearest matching ancestor
+            let (rule_index, ancestor) = match self.outcome(process, &mut outcomes) {
+                MatchOutcome::Matched { rule_index, .. } => (rule_index, None),
+                MatchOutcome::Excluded { pattern } => {
+                    log::debug!("Process {} is in exclude list ({}), skipping", process.name, pattern);
+                    continue;
+                }
+                MatchOutcome::OutsideSchedule { .. } | MatchOutcome::NoMatch => {
+                    match self.inherited_rule(process.pid, &tree, &mut outcomes) {
+                        Some((rule_index, ancestor)) => (rule_index, Some(ancestor)),
+                        None => continue,
+                    }
+                }
+            };
 
             // Try to set affinity with retries
-            match self.set_affinity_with_retry(process.pid, &process.name) {
+            let Some(rule) = self.rules.get(rule_index) else {
+                continue;
+            };
+            let target = rule.target_for(power_state).clone();
+            // Read before the first pin only, so a process moved between
+            // rules still reverts to what it started with
+            let original = match self.applied.get(&process.pid) {
+                Some(applied) => applied.original.clone(),
+                None => self.affinity_manager.get_affinity(process.pid).ok(),
+            };
+            match self.set_affinity_with_retry(process.pid, &process.name, &target, rule.single_numa_node) {
                 Ok(_) => {
+                    match ancestor {
+                        Some(ancestor) => log::info!(
+                            "Successfully set {} affinity for process {} (PID: {}) by rule {}, inherited from {} (PID: {})",
+                            target,
+                            process.name,
+                            process.pid,
+                            rule.name,
+                            ancestor.name,
+                            ancestor.pid
+                        ),
+                        None => log::info!(
+                            "Successfully set {} affinity for process {} (PID: {}) by rule {}",
+                            target,
+                            process.name,
+                            process.pid,
+                            rule.name
+                        ),
+                    }
+                    self.applied.insert(
+                        process.pid,
+                        AppliedAffinity {
+                            rule_index,
+                            target,
+                            original,
+                            demoted: false,
+                        },
+                    );
                     self.cache.mark_processed(process.pid);
+                    pinned.insert(process.pid);
                     processed_count += 1;
-                    log::info!(
-                        "Successfully set P-core affinity for process {} (PID: {})",
-                        process.name,
-                        process.pid
-                    );
                 }
                 Err(e) => {
                     log::warn!(
@@ -77,89 +242,292 @@ impl ProcessManager {
             }
         }
 
+        // Before sampling, so a process that just got focus is not demoted
+        let focused = self.focused_pid();
+        self.update_usage(&tree, &pinned, focused);
+        self.update_focus(&tree, &pinned, focused);
+
         Ok(processed_count)
     }
 
-    pub fn cleanup_cache(&mut self) -> usize {
-        self.cache.cleanup()
+    /// Sample the CPU time of pinned processes whose rule has
+    /// `idle_demotion`, demote those that stayed idle over the window and
+    /// promote demoted ones that got busy again
+    fn update_usage(&mut self, tree: &ProcessTree, pinned: &HashSet<u32>, focused: Option<u32>) {
+        let now = self.clock.now();
+        self.usage.retain(|pid, _| self.applied.contains_key(pid) && !pinned.contains(pid));
+
+        let mut pids: Vec<u32> = self.applied.keys().copied().collect();
+        pids.sort_unstable();
+        for pid in pids {
+            let Some(applied) = self.applied.get(&pid) else {
+                continue;
+            };
+            let (rule_index, demoted, pinned_to) = (applied.rule_index, applied.demoted, applied.target.clone());
+            let Some(idle) = self.rules.get(rule_index).and_then(|rule| rule.idle_demotion.clone()) else {
+                continue;
+            };
+            // The focused process is in use; sampling starts over once it
+            // loses focus
+            if focused == Some(pid) {
+                self.usage.remove(&pid);
+                continue;
+            }
+            let (Some(process), Some(cpu_time)) = (tree.get(pid), self.source.cpu_time(pid)) else {
+                continue;
+            };
+
+            let samples = self.usage.entry(pid).or_default();
+            samples.record(now, cpu_time, idle.window);
+            let Some(percent) = samples.average_percent(idle.window) else {
+                continue;
+            };
+
+            let demote = idle.should_demote(demoted, percent);
+            if demote == demoted {
+                continue;
+            }
+            let seconds = idle.window.num_seconds();
+            let (target, reason) = if demote {
+                (idle.target, format!("idle at {:.1}% CPU over {}s", percent, seconds))
+            } else {
+                (pinned_to, format!("busy again at {:.1}% CPU over {}s", percent, seconds))
+            };
+            if self.retarget(process, rule_index, &target, &reason) {
+                if let Some(applied) = self.applied.get_mut(&pid) {
+                    applied.demoted = demote;
+                }
+            }
+        }
     }
 
-    pub fn get_cache_stats(&self) -> String {
-        let stats = self.cache.stats();
-        format!(
-            "Cache stats - Total: {}, Processed: {}, Unprocessed: {}",
-            stats.total_entries, stats.processed_count, stats.unprocessed_count
-        )
+    /// The foreground process, if it is pinned by a rule with `boost_on_focus`
+    fn focused_pid(&self) -> Option<u32> {
+        self.focus.foreground_pid().filter(|pid| {
+            self.applied
+                .get(pid)
+                .and_then(|applied| self.rules.get(applied.rule_index))
+                .is_some_and(|rule| rule.boost_on_focus)
+        })
     }
 
-    fn is_whitelisted(&self, process_name: &str) -> bool {
-        if self.config.whitelist.processes.is_empty() {
-            return false;
+    /// Boost the focused process, and return the previously boosted one to
+    /// its rule's target
+    fn update_focus(&mut self, tree: &ProcessTree, pinned: &HashSet<u32>, focused: Option<u32>) {
+        if let Some(pid) = self.boosted.filter(|pid| Some(*pid) != focused) {
+            self.boosted = None;
+            // Reverted or re-pinned processes already left the boost target
+            if !pinned.contains(&pid) {
+                if let (Some(process), Some(applied)) = (tree.get(pid), self.applied.get(&pid)) {
+                    let target = applied.target.clone();
+                    self.retarget(process, applied.rule_index, &target, "focus moved away");
+                }
+            }
         }
 
-        match self.match_mode {
-            MatchMode::Exact => self.exact_match(process_name),
-            MatchMode::Wildcard => self.wildcard_match(process_name),
-            MatchMode::Regex => self.regex_match(process_name),
+        let Some(pid) = focused else {
+            return;
+        };
+        // Boost again if this scan pinned it back to its rule's target
+        if self.boosted == Some(pid) && !pinned.contains(&pid) {
+            return;
+        }
+        self.boosted = Some(pid);
+
+        if let (Some(process), Some(applied)) = (tree.get(pid), self.applied.get(&pid)) {
+            let rule_index = applied.rule_index;
+            if let Some(target) = self.rules.get(rule_index).map(|rule| rule.focus_target.clone()) {
+                // Leaving the boost target restores the rule's target, not the idle one
+                if self.retarget(process, rule_index, &target, "in the foreground") {
+                    if let Some(applied) = self.applied.get_mut(&pid) {
+                        applied.demoted = false;
+                    }
+                }
+            }
         }
     }
 
-    fn is_excluded(&self, process_name: &str) -> bool {
-        let name_lower = process_name.to_lowercase();
-        self.config
-            .whitelist
-            .exclude_processes
-            .iter()
-            .any(|excluded| name_lower.contains(&excluded.to_lowercase()))
+    /// Move a pinned process to another target of its rule; `false` if it
+    /// could not be moved
+    fn retarget(&self, process: &ProcessInfo, rule_index: usize, target: &CoreTarget, reason: &str) -> bool {
+        let Some(rule) = self.rules.get(rule_index) else {
+            return false;
+        };
+
+        match self.set_affinity_with_retry(process.pid, &process.name, target, rule.single_numa_node) {
+            Ok(_) => {
+                log::info!(
+                    "Set {} affinity for process {} (PID: {}) by rule {}: {}",
+                    target,
+                    process.name,
+                    process.pid,
+                    rule.name,
+                    reason
+                );
+                true
+            }
+            Err(e) => {
+                log::warn!(
+                    "Failed to set affinity for process {} (PID: {}): {}",
+                    process.name,
+                    process.pid,
+                    e
+                );
+                false
+            }
+        }
     }
 
-    fn exact_match(&self, process_name: &str) -> bool {
-        let name_lower = process_name.to_lowercase();
-        self.config
-            .whitelist
-            .processes
+    /// Decide again for every pinned process after a schedule window opened
+    /// or closed or the power source changed: processes whose rule or target
+    /// changed are picked up by the scan, and those no rule applies to any
+    /// more get their affinity back
+    fn reapply_pinned(&mut self, tree: &ProcessTree, outcomes: &mut HashMap<u32, MatchOutcome>) {
+        let power_state = self.rules.power_state();
+        let pinned: Vec<(u32, usize, CoreTarget)> = self
+            .applied
             .iter()
-            .any(|pattern| pattern.to_lowercase() == name_lower)
+            .map(|(pid, applied)| (*pid, applied.rule_index, applied.target.clone()))
+            .collect();
+
+        for (pid, pinned_by, pinned_to) in pinned {
+            let Some(process) = tree.get(pid) else {
+                self.applied.remove(&pid);
+                continue;
+            };
+
+            let rule_index = match self.outcome(process, outcomes) {
+                MatchOutcome::Matched { rule_index, .. } => Some(rule_index),
+                MatchOutcome::Excluded { .. } => None,
+                MatchOutcome::OutsideSchedule { .. } | MatchOutcome::NoMatch => {
+                    self.inherited_rule(pid, tree, outcomes).map(|(rule_index, _)| rule_index)
+                }
+            };
+
+            let target = rule_index
+                .and_then(|rule_index| self.rules.get(rule_index))
+                .map(|rule| rule.target_for(power_state));
+            match rule_index {
+                Some(rule_index) if rule_index == pinned_by && target == Some(&pinned_to) => {}
+                Some(_) => self.cache.unmark_processed(pid),
+                None => self.revert(process, pinned_by),
+            }
+        }
     }
 
-    fn wildcard_match(&self, process_name: &str) -> bool {
-        use wildmatch::WildMatch;
+    /// Restore the affinity a process had before it was pinned
+    fn revert(&mut self, process: &ProcessInfo, pinned_by: usize) {
+        let Some(applied) = self.applied.remove(&process.pid) else {
+            return;
+        };
+        // Unmatched processes are checked on every scan, so the rule can
+        // pin it again when its window reopens
+        self.cache.unmark_processed(process.pid);
 
-        let name_lower = process_name.to_lowercase();
-        self.config
-            .whitelist
-            .processes
-            .iter()
-            .any(|pattern| {
-                let pattern_lower = pattern.to_lowercase();
-                WildMatch::new(&pattern_lower).matches(&name_lower)
+        let rule_name = self.rules.get(pinned_by).map(|rule| rule.name.as_str()).unwrap_or_default();
+        let Some(original) = applied.original else {
+            log::warn!(
+                "Rule {} no longer applies to process {} (PID: {}), but its original affinity is unknown; leaving it pinned",
+                rule_name,
+                process.name,
+                process.pid
+            );
+            return;
+        };
+
+        match self.affinity_manager.set_affinity(process.pid, &original, &process.name) {
+            Ok(_) => log::info!(
+                "Reverted affinity of process {} (PID: {}) to {}: rule {} no longer applies",
+                process.name,
+                process.pid,
+                original,
+                rule_name
+            ),
+            Err(e) => log::warn!(
+                "Failed to revert affinity for process {} (PID: {}): {}",
+                process.name,
+                process.pid,
+                e
+            ),
+        }
+    }
+
+    fn outcome(&self, process: &ProcessInfo, outcomes: &mut HashMap<u32, MatchOutcome>) -> MatchOutcome {
+        outcomes
+            .entry(process.pid)
+            .or_insert_with(|| {
+                let view = ProcessView::new(process, self.source.as_ref());
+                self.rules.evaluate(&view).outcome
             })
+            .clone()
     }
 
-    fn regex_match(&self, process_name: &str) -> bool {
-        use regex::Regex;
+    /// The rule of the nearest ancestor whose rule reaches down to `pid`
+    fn inherited_rule<'a>(
+        &self,
+        pid: u32,
+        tree: &ProcessTree<'a>,
+        outcomes: &mut HashMap<u32, MatchOutcome>,
+    ) -> Option<(usize, &'a ProcessInfo)> {
+        // Skip the walk, and its start time lookups, when no rule is inherited
+        if !self.rules.rules().iter().any(|rule| rule.inherit_children) {
+            return None;
+        }
 
-        self.config
-            .whitelist
-            .processes
-            .iter()
-            .any(|pattern| {
-                if let Ok(re) = Regex::new(pattern) {
-                    re.is_match(process_name)
-                } else {
-                    false
-                }
-            })
+        tree.ancestors(pid, |pid| self.source.start_time(pid)).find_map(|(ancestor, depth)| {
+            let MatchOutcome::Matched { rule_index, .. } = self.outcome(ancestor, outcomes) else {
+                return None;
+            };
+            let rule = self.rules.get(rule_index)?;
+            rule.inherited_at(depth).then_some((rule_index, ancestor))
+        })
+    }
+
+    /// Drop cache entries for exited processes and stale entries
+    pub fn cleanup_cache(&mut self) -> usize {
+        self.applied.retain(|pid, _| self.live_pids.contains(pid));
+        self.usage.retain(|pid, _| self.live_pids.contains(pid));
+        self.cache.remove_exited(&self.live_pids) + self.cache.cleanup()
     }
 
-    fn set_affinity_with_retry(&self, pid: u32, process_name: &str) -> Result<(), ServiceError> {
+    pub fn get_cache_stats(&self) -> String {
+        let stats = self.cache.stats();
+        format!(
+            "Cache stats - Total: {}, Processed: {}, Unprocessed: {}",
+            stats.total_entries, stats.processed_count, stats.unprocessed_count
+        )
+    }
+
+    /// Which rule applies to a process name, and every pattern tried first
+    ///
+    /// Only name patterns can match, since a bare name has no executable path.
+    pub fn explain(&self, process_name: &str) -> MatchDecision {
+        self.rules.explain(&ProcessView::from_name(process_name))
+    }
+
+    fn set_affinity_with_retry(
+        &self,
+        pid: u32,
+        process_name: &str,
+        target: &CoreTarget,
+        single_numa_node: bool,
+    ) -> Result<(), ServiceError> {
         let mut last_error = None;
 
         for attempt in 1..=self.config.advanced.retry_attempts {
-            match self.affinity_manager.set_affinity_to_p_cores(pid, process_name) {
+            match self
+                .affinity_manager
+                .set_affinity_to_target(pid, target, single_numa_node, process_name)
+            {
                 Ok(_) => return Ok(()),
                 Err(e) => {
+                    // Access denied, an exited process or an empty target
+                    // will fail the same way again
+                    let transient = matches!(e, ServiceError::TransientAffinity(_));
                     last_error = Some(e);
+                    if !transient {
+                        break;
+                    }
                     if attempt < self.config.advanced.retry_attempts {
                         log::debug!(
                             "Retry {}/{} for process {} (PID: {})",
@@ -184,6 +552,7 @@ impl ProcessManager {
 mod tests {
     use super::*;
     use crate::cpu::types::CoreInfo;
+    use crate::cpu::CpuSet;
 
     fn create_test_config() -> Arc<Config> {
         let mut config = Config::default();
@@ -193,32 +562,28 @@ mod tests {
 
     fn create_test_manager() -> ProcessManager {
         let config = create_test_config();
-        let core_info = Arc::new(CoreInfo::all_cores(8));
+        let core_info = Arc::new(CoreInfo::all_cores(CpuSet::from_cpus(0..8)));
         let affinity_manager = Arc::new(AffinityManager::new(core_info));
-        ProcessManager::new(config, affinity_manager)
+        ProcessManager::new(config, affinity_manager).unwrap()
     }
 
-    #[test]
-    fn test_exact_match() {
-        let manager = create_test_manager();
-        assert!(manager.exact_match("test.exe"));
-        assert!(manager.exact_match("TEST.EXE"));
-        assert!(!manager.exact_match("other.exe"));
+    fn is_excluded(manager: &ProcessManager, process_name: &str) -> bool {
+        matches!(manager.explain(process_name).outcome, MatchOutcome::Excluded { .. })
     }
 
     #[test]
-    fn test_wildcard_match() {
+    fn test_match_rule() {
         let manager = create_test_manager();
-        assert!(manager.wildcard_match("my.game.exe"));
-        assert!(manager.wildcard_match("best.game.exe"));
-        assert!(!manager.wildcard_match("game.txt"));
+        assert_eq!(manager.explain("TEST.EXE").rule_index(), Some(0));
+        assert_eq!(manager.explain("my.game.exe").rule_index(), Some(0));
+        assert_eq!(manager.explain("other.exe").rule_index(), None);
     }
 
     #[test]
     fn test_exclude() {
         let manager = create_test_manager();
-        assert!(manager.is_excluded("svchost.exe"));
-        assert!(manager.is_excluded("SYSTEM"));
-        assert!(!manager.is_excluded("chrome.exe"));
+        assert!(is_excluded(&manager, "svchost.exe"));
+        assert!(is_excluded(&manager, "SYSTEM"));
+        assert!(!is_excluded(&manager, "chrome.exe"));
     }
 }
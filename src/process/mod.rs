@@ -1,7 +1,30 @@
 pub mod cache;
 pub mod manager;
+#[cfg(windows)]
 pub mod monitor;
+pub mod pattern;
+pub mod procfs;
+pub mod rules;
+pub mod schedule;
+pub mod source;
+pub mod trace;
+pub mod tree;
+pub mod usage;
+pub mod view;
 
 pub use cache::ProcessCache;
 pub use manager::ProcessManager;
+#[cfg(windows)]
 pub use monitor::ProcessMonitor;
+pub use pattern::{Pattern, PatternError, PatternSet};
+pub use procfs::ProcFsSource;
+pub use rules::{build_rules, MatchDecision, MatchOutcome, PatternAttempt, Rule, RuleSet};
+pub use schedule::{Schedule, ScheduleError, ScheduleWindow};
+pub use source::{default_source, ProcessInfo, ProcessOwner, ProcessSource};
+pub use trace::{
+    RecordingFocusProvider, RecordingPowerSource, RecordingSource, ReplayClock, ReplayFocusProvider,
+    ReplayPowerSource, ReplaySource, TraceFrame, TraceHeader, TracedAttributes,
+};
+pub use tree::ProcessTree;
+pub use usage::{CpuSamples, IdleDemotion, IdleDemotionError};
+pub use view::ProcessView;
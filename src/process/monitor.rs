@@ -1,16 +1,25 @@
+use crate::process::source::{ProcessInfo, ProcessOwner, ProcessSource};
 use crate::utils::ServiceError;
-use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use std::ffi::OsString;
+use std::os::windows::ffi::OsStringExt;
+use std::path::PathBuf;
+use std::time::Duration;
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Foundation::{CloseHandle, FILETIME, HANDLE, UNICODE_STRING};
+use windows::Win32::Security::{GetTokenInformation, LookupAccountSidW, TokenUser, SID_NAME_USE, TOKEN_QUERY, TOKEN_USER};
 use windows::Win32::System::Diagnostics::ToolHelp::{
     CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS,
 };
+use windows::Win32::System::RemoteDesktop::ProcessIdToSessionId;
+use windows::Win32::System::Threading::{
+    GetProcessTimes, NtQueryInformationProcess, OpenProcess, OpenProcessToken, ProcessCommandLineInformation,
+    QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+};
 
-#[derive(Debug, Clone)]
-pub struct ProcessInfo {
-    pub pid: u32,
-    pub name: String,
-    pub parent_pid: u32,
-}
+/// Longest path `QueryFullProcessImageNameW` can return, in UTF-16 units
+const MAX_IMAGE_PATH: usize = 32768;
 
+/// Process source backed by the Toolhelp32 snapshot API
 pub struct ProcessMonitor;
 
 impl ProcessMonitor {
@@ -100,6 +109,198 @@ impl ProcessMonitor {
         // Convert to String
         String::from_utf16_lossy(&sz_exe_file[..len])
     }
+
+    /// Read the command line with `ProcessCommandLineInformation`, which
+    /// returns a `UNICODE_STRING` followed by the characters it points to
+    fn query_cmdline(handle: HANDLE) -> Option<String> {
+        // The first call fails with the required size
+        let mut size = 0u32;
+        let _ = unsafe {
+            NtQueryInformationProcess(handle, ProcessCommandLineInformation, std::ptr::null_mut(), 0, &mut size)
+        };
+        if (size as usize) < std::mem::size_of::<UNICODE_STRING>() {
+            return None;
+        }
+
+        // u64 elements keep the UNICODE_STRING header aligned
+        let mut buffer = vec![0u64; (size as usize).div_ceil(8)];
+        unsafe {
+            NtQueryInformationProcess(
+                handle,
+                ProcessCommandLineInformation,
+                buffer.as_mut_ptr().cast(),
+                size,
+                &mut size,
+            )
+        }
+        .ok()?;
+
+        let header = unsafe { &*(buffer.as_ptr() as *const UNICODE_STRING) };
+        if header.Buffer.is_null() || header.Length == 0 {
+            return None;
+        }
+
+        let chars = unsafe { std::slice::from_raw_parts(header.Buffer.0, header.Length as usize / 2) };
+        Some(String::from_utf16_lossy(chars))
+    }
+
+    /// Resolve the user of a process token to `DOMAIN\user`
+    fn query_token_user(token: HANDLE) -> Option<String> {
+        // The first call fails with the required size
+        let mut size = 0u32;
+        let _ = unsafe { GetTokenInformation(token, TokenUser, None, 0, &mut size) };
+        if (size as usize) < std::mem::size_of::<TOKEN_USER>() {
+            return None;
+        }
+
+        // u64 elements keep the TOKEN_USER header aligned
+        let mut buffer = vec![0u64; (size as usize).div_ceil(8)];
+        let result = unsafe { GetTokenInformation(token, TokenUser, Some(buffer.as_mut_ptr().cast()), size, &mut size) };
+        if !result.as_bool() {
+            return None;
+        }
+        let sid = unsafe { (*(buffer.as_ptr() as *const TOKEN_USER)).User.Sid };
+
+        let mut name = [0u16; 256];
+        let mut domain = [0u16; 256];
+        let mut name_len = name.len() as u32;
+        let mut domain_len = domain.len() as u32;
+        let mut sid_use = SID_NAME_USE::default();
+        let result = unsafe {
+            LookupAccountSidW(
+                PCWSTR::null(),
+                sid,
+                PWSTR(name.as_mut_ptr()),
+                &mut name_len,
+                PWSTR(domain.as_mut_ptr()),
+                &mut domain_len,
+                &mut sid_use,
+            )
+        };
+        if !result.as_bool() {
+            return None;
+        }
+
+        let name = String::from_utf16_lossy(&name[..name_len as usize]);
+        let domain = String::from_utf16_lossy(&domain[..domain_len as usize]);
+        if domain.is_empty() {
+            Some(name)
+        } else {
+            Some(format!("{}\\{}", domain, name))
+        }
+    }
+}
+
+impl ProcessSource for ProcessMonitor {
+    fn snapshot(&mut self) -> Result<Vec<ProcessInfo>, ServiceError> {
+        Self::get_all_processes()
+    }
+
+    fn exe_path(&self, pid: u32) -> Option<PathBuf> {
+        let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) }.ok()?;
+
+        let mut buffer = vec![0u16; MAX_IMAGE_PATH];
+        let mut size = buffer.len() as u32;
+        let result = unsafe {
+            QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut size)
+        };
+
+        unsafe {
+            let _ = CloseHandle(handle);
+        }
+
+        if !result.as_bool() {
+            log::debug!("Failed to query image path (PID: {})", pid);
+            return None;
+        }
+
+        Some(PathBuf::from(OsString::from_wide(&buffer[..size as usize])))
+    }
+
+    fn cmdline(&self, pid: u32) -> Option<String> {
+        let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) }.ok()?;
+        let cmdline = Self::query_cmdline(handle);
+
+        unsafe {
+            let _ = CloseHandle(handle);
+        }
+
+        if cmdline.is_none() {
+            log::debug!("Failed to query command line (PID: {})", pid);
+        }
+        cmdline
+    }
+
+    fn owner(&self, pid: u32) -> Option<ProcessOwner> {
+        let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) }.ok()?;
+
+        let mut token = HANDLE::default();
+        let name = if unsafe { OpenProcessToken(handle, TOKEN_QUERY, &mut token) }.as_bool() {
+            let name = Self::query_token_user(token);
+            unsafe {
+                let _ = CloseHandle(token);
+            }
+            name
+        } else {
+            None
+        };
+
+        unsafe {
+            let _ = CloseHandle(handle);
+        }
+
+        match name {
+            Some(name) => Some(ProcessOwner {
+                name: Some(name),
+                uid: None,
+            }),
+            None => {
+                log::debug!("Failed to query process owner (PID: {})", pid);
+                None
+            }
+        }
+    }
+
+    fn session(&self, pid: u32) -> Option<u32> {
+        let mut session = 0u32;
+        let result = unsafe { ProcessIdToSessionId(pid, &mut session) };
+        result.as_bool().then_some(session)
+    }
+
+    fn cpu_time(&self, pid: u32) -> Option<Duration> {
+        let (_, kernel, user) = process_times(pid)?;
+        Some(kernel + user)
+    }
+
+    fn start_time(&self, pid: u32) -> Option<Duration> {
+        let (created, _, _) = process_times(pid)?;
+        Some(created)
+    }
+}
+
+/// Creation time since 1601, kernel time and user time of a process
+fn process_times(pid: u32) -> Option<(Duration, Duration, Duration)> {
+    let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) }.ok()?;
+
+    let (mut created, mut exited) = (FILETIME::default(), FILETIME::default());
+    let (mut kernel, mut user) = (FILETIME::default(), FILETIME::default());
+    let result = unsafe { GetProcessTimes(handle, &mut created, &mut exited, &mut kernel, &mut user) };
+
+    unsafe {
+        let _ = CloseHandle(handle);
+    }
+
+    if !result.as_bool() {
+        log::debug!("Failed to query process times (PID: {})", pid);
+        return None;
+    }
+
+    // FILETIME values count 100ns intervals
+    let duration = |time: FILETIME| {
+        let intervals = (u64::from(time.dwHighDateTime) << 32) | u64::from(time.dwLowDateTime);
+        Duration::from_secs(intervals / 10_000_000) + Duration::from_nanos(intervals % 10_000_000 * 100)
+    };
+    Some((duration(created), duration(kernel), duration(user)))
 }
 
 #[cfg(test)]
@@ -120,6 +321,39 @@ mod tests {
         assert!(found, "Current process not found in process list");
     }
 
+    #[test]
+    fn test_exe_path() {
+        let path = ProcessMonitor.exe_path(std::process::id()).unwrap();
+        assert_eq!(path, std::env::current_exe().unwrap());
+    }
+
+    #[test]
+    fn test_cmdline() {
+        let cmdline = ProcessMonitor.cmdline(std::process::id()).unwrap();
+        let exe = std::env::current_exe().unwrap();
+        let stem = exe.file_stem().unwrap().to_string_lossy();
+        assert!(cmdline.contains(stem.as_ref()));
+    }
+
+    #[test]
+    fn test_owner_and_session() {
+        let pid = std::process::id();
+        let owner = ProcessMonitor.owner(pid).unwrap();
+        assert!(owner.user_name().is_some_and(|name| !name.is_empty()));
+        assert!(ProcessMonitor.session(pid).is_some());
+    }
+
+    #[test]
+    fn test_cpu_time() {
+        let pid = std::process::id();
+        let before = ProcessMonitor.cpu_time(pid).unwrap();
+        let spin = std::time::Instant::now();
+        while spin.elapsed() < Duration::from_millis(50) {
+            std::hint::black_box(spin.elapsed());
+        }
+        assert!(ProcessMonitor.cpu_time(pid).unwrap() >= before);
+    }
+
     #[test]
     fn test_process_names() {
         let processes = ProcessMonitor::get_all_processes().unwrap();
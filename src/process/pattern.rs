@@ -0,0 +1,319 @@
+use crate::config::settings::MatchMode;
+use regex::{Regex, RegexSet, SetMatches};
+use std::fmt;
+use thiserror::Error;
+use wildmatch::WildMatch;
+
+/// Error for a pattern that could not be compiled
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid regex {pattern:?}: {reason}")]
+pub struct PatternError {
+    pub pattern: String,
+    pub reason: String,
+}
+
+/// One entry of a process pattern list
+///
+/// A config entry can pick its matcher with an `exact:`, `glob:` or `re:`
+/// prefix; entries without one use the mode of the list they are in. Regexes
+/// are only compiled as part of a `PatternSet`.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    pub mode: MatchMode,
+    pub text: String,
+    /// The entry as written, prefix included
+    entry: String,
+    matcher: Matcher,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    /// Lowercased, compared against the lowercased value
+    Exact(String),
+    Wildcard(WildMatch),
+    /// Source for the set's `RegexSet`
+    Regex(String),
+}
+
+impl Pattern {
+    /// Read a config entry, honouring an `exact:`, `glob:` or `re:` prefix
+    pub fn parse(entry: &str, default_mode: &MatchMode) -> Self {
+        match Self::split_prefix(entry) {
+            Some((mode, text)) => Self::build(entry, text, mode, false),
+            None => Self::build(entry, entry, default_mode.clone(), false),
+        }
+    }
+
+    /// Like `parse`, but an entry without a prefix that falls back to regex
+    /// mode must match the whole value, as exact and glob entries do
+    pub fn parse_anchored(entry: &str, default_mode: &MatchMode) -> Self {
+        match Self::split_prefix(entry) {
+            Some((mode, text)) => Self::build(entry, text, mode, false),
+            None => Self::build(entry, entry, default_mode.clone(), true),
+        }
+    }
+
+    fn split_prefix(entry: &str) -> Option<(MatchMode, &str)> {
+        [
+            ("exact:", MatchMode::Exact),
+            ("glob:", MatchMode::Wildcard),
+            ("re:", MatchMode::Regex),
+        ]
+        .into_iter()
+        .find_map(|(prefix, mode)| entry.strip_prefix(prefix).map(|text| (mode, text)))
+    }
+
+    fn build(entry: &str, text: &str, mode: MatchMode, anchored: bool) -> Self {
+        let matcher = match mode {
+            MatchMode::Exact => Matcher::Exact(text.to_lowercase()),
+            MatchMode::Wildcard => Matcher::Wildcard(WildMatch::new_case_insensitive(text)),
+            MatchMode::Regex if anchored => Matcher::Regex(format!("^(?:{})$", text)),
+            MatchMode::Regex => Matcher::Regex(text.to_string()),
+        };
+
+        Self {
+            mode,
+            text: text.to_string(),
+            entry: entry.to_string(),
+            matcher,
+        }
+    }
+
+    /// Whether the entry picked its matcher with a prefix
+    pub fn has_prefix(&self) -> bool {
+        self.entry.len() != self.text.len()
+    }
+
+    /// The entry as written in the config
+    pub fn as_str(&self) -> &str {
+        &self.entry
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = match self.mode {
+            MatchMode::Exact => "exact:",
+            MatchMode::Wildcard => "glob:",
+            MatchMode::Regex => "re:",
+        };
+        write!(f, "{}{}", prefix, self.text)
+    }
+}
+
+/// The patterns of one config list, compiled together
+///
+/// Regexes are combined into one `RegexSet`, compiled once at load, so a
+/// value is scanned once for all of them, and a value is lowercased at most
+/// once per lookup.
+#[derive(Debug, Clone, Default)]
+pub struct PatternSet {
+    patterns: Vec<Pattern>,
+    regexes: Option<RegexSet>,
+    /// Index into `regexes` of each pattern that is a regex
+    regex_slots: Vec<Option<usize>>,
+}
+
+impl PatternSet {
+    /// Compile every entry, honouring per-entry prefixes
+    pub fn parse(entries: &[String], default_mode: &MatchMode) -> Result<Self, PatternError> {
+        Self::from_patterns(entries.iter().map(|entry| Pattern::parse(entry, default_mode)).collect())
+    }
+
+    /// Compile every entry with `Pattern::parse_anchored`
+    pub fn parse_anchored(entries: &[String], default_mode: &MatchMode) -> Result<Self, PatternError> {
+        Self::from_patterns(entries.iter().map(|entry| Pattern::parse_anchored(entry, default_mode)).collect())
+    }
+
+    fn from_patterns(patterns: Vec<Pattern>) -> Result<Self, PatternError> {
+        let mut regex_patterns = Vec::new();
+        let regex_slots = patterns
+            .iter()
+            .map(|pattern| match &pattern.matcher {
+                Matcher::Regex(source) => {
+                    regex_patterns.push((source.as_str(), pattern.text.as_str()));
+                    Some(regex_patterns.len() - 1)
+                }
+                _ => None,
+            })
+            .collect();
+
+        let regexes = if regex_patterns.is_empty() {
+            None
+        } else {
+            Some(compile_regex_set(&regex_patterns)?)
+        };
+
+        Ok(Self {
+            patterns,
+            regexes,
+            regex_slots,
+        })
+    }
+
+    pub fn patterns(&self) -> &[Pattern] {
+        &self.patterns
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Position of the first pattern matching `value`, in list order
+    pub fn first_match(&self, value: &str) -> Option<usize> {
+        let mut lookup = Lookup::new(value);
+        (0..self.patterns.len()).find(|&index| self.matches_with(&mut lookup, index))
+    }
+
+    /// Whether the pattern at `index` matches `value`
+    pub fn matches_at(&self, index: usize, value: &str) -> bool {
+        self.matches_with(&mut Lookup::new(value), index)
+    }
+
+    fn matches_with(&self, lookup: &mut Lookup<'_>, index: usize) -> bool {
+        let value = lookup.value;
+        match &self.patterns[index].matcher {
+            Matcher::Exact(pattern) => *lookup.lowered.get_or_insert_with(|| value.to_lowercase()) == *pattern,
+            Matcher::Wildcard(wildcard) => wildcard.matches(value),
+            Matcher::Regex(_) => {
+                let hits = lookup
+                    .regex_hits
+                    .get_or_insert_with(|| self.regexes.as_ref().map(|set| set.matches(value)));
+                match (hits, self.regex_slots[index]) {
+                    (Some(hits), Some(slot)) => hits.matched(slot),
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    /// The first pattern matching `value`
+    pub fn matching(&self, value: &str) -> Option<&Pattern> {
+        self.first_match(value).map(|index| &self.patterns[index])
+    }
+
+    pub fn is_match(&self, value: &str) -> bool {
+        self.first_match(value).is_some()
+    }
+}
+
+/// A value being matched, with what has been derived from it so far
+struct Lookup<'v> {
+    value: &'v str,
+    lowered: Option<String>,
+    regex_hits: Option<Option<SetMatches>>,
+}
+
+impl<'v> Lookup<'v> {
+    fn new(value: &'v str) -> Self {
+        Self {
+            value,
+            lowered: None,
+            regex_hits: None,
+        }
+    }
+}
+
+/// Compile `(source, text)` regexes into one set
+///
+/// `RegexSet` does not say which regex it rejected, so on failure each one is
+/// compiled on its own to name the culprit in the error.
+pub(crate) fn compile_regex_set(regexes: &[(&str, &str)]) -> Result<RegexSet, PatternError> {
+    RegexSet::new(regexes.iter().map(|&(source, _)| source)).map_err(|set_error| {
+        regexes
+            .iter()
+            .find_map(|&(source, text)| {
+                Regex::new(source).err().map(|e| PatternError {
+                    pattern: text.to_string(),
+                    reason: e.to_string(),
+                })
+            })
+            .unwrap_or_else(|| PatternError {
+                pattern: regexes.iter().map(|&(_, text)| text).collect::<Vec<_>>().join(", "),
+                reason: set_error.to_string(),
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(entry: &str, default_mode: MatchMode) -> PatternSet {
+        PatternSet::parse(&[entry.to_string()], &default_mode).unwrap()
+    }
+
+    fn exact_match(pattern: &str, value: &str) -> bool {
+        parse(pattern, MatchMode::Exact).is_match(value)
+    }
+
+    fn wildcard_match(pattern: &str, value: &str) -> bool {
+        parse(pattern, MatchMode::Wildcard).is_match(value)
+    }
+
+    #[test]
+    fn test_exact_match() {
+        assert!(exact_match("test.exe", "test.exe"));
+        assert!(exact_match("test.exe", "TEST.EXE"));
+        assert!(!exact_match("test.exe", "other.exe"));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.game.exe", "my.game.exe"));
+        assert!(wildcard_match("*.game.exe", "best.game.exe"));
+        assert!(!wildcard_match("*.game.exe", "game.txt"));
+        assert!(wildcard_match("*.GAME.exe", "My.Game.EXE"));
+    }
+
+    #[test]
+    fn test_prefixes() {
+        let set = parse("system", MatchMode::Wildcard);
+        assert_eq!(set.patterns()[0].mode, MatchMode::Wildcard);
+        assert!(set.is_match("System"));
+        assert!(!set.is_match("systemsettings.exe"));
+
+        let set = parse("re:^system", MatchMode::Exact);
+        assert_eq!(set.patterns()[0].text, "^system");
+        assert!(set.matches_at(0, "systemsettings"));
+
+        let set = parse("glob:*.game.exe", MatchMode::Exact);
+        assert!(set.is_match("my.game.exe"));
+        assert_eq!(set.patterns()[0].to_string(), "glob:*.game.exe");
+
+        assert!(!parse("exact:*.exe", MatchMode::Wildcard).is_match("a.exe"));
+
+        // Anchoring applies to regex entries without a prefix
+        let set = PatternSet::parse_anchored(&["sys".to_string(), "re:sys".to_string()], &MatchMode::Regex).unwrap();
+        assert_eq!(set.first_match("system"), Some(1));
+        assert_eq!(set.first_match("sys"), Some(0));
+    }
+
+    #[test]
+    fn test_pattern_set() {
+        let entries: Vec<String> = ["exact:Code.exe", "re:^back(up)?", "*.game.exe", "re:agent$"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let set = PatternSet::parse(&entries, &MatchMode::Wildcard).unwrap();
+
+        assert_eq!(set.first_match("code.EXE"), Some(0));
+        assert_eq!(set.first_match("backupagent"), Some(1));
+        assert_eq!(set.first_match("sync-agent"), Some(3));
+        assert_eq!(set.matching("my.game.exe").map(Pattern::as_str), Some("*.game.exe"));
+        assert!(!set.is_match("notepad.exe"));
+        assert!(PatternSet::default().first_match("x").is_none());
+
+        let error = PatternSet::parse(&["(unclosed".to_string()], &MatchMode::Regex).unwrap_err();
+        assert_eq!(error.pattern, "(unclosed");
+        assert!(error.to_string().starts_with("invalid regex \"(unclosed\""));
+
+        // The failing entry is named even among valid ones
+        let entries = ["re:ok".to_string(), "re:[z-a]".to_string()];
+        assert_eq!(PatternSet::parse(&entries, &MatchMode::Exact).unwrap_err().pattern, "[z-a]");
+    }
+}
@@ -0,0 +1,335 @@
+use crate::process::source::{ProcessInfo, ProcessOwner, ProcessSource};
+use crate::utils::ServiceError;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Process source backed by the Linux `/proc` filesystem
+pub struct ProcFsSource {
+    root: PathBuf,
+}
+
+impl ProcFsSource {
+    pub fn new() -> Self {
+        Self::with_root("/proc")
+    }
+
+    /// Read processes from an alternative procfs root (used by tests)
+    pub fn with_root<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn get_all_processes(&self) -> Result<Vec<ProcessInfo>, ServiceError> {
+        let entries = fs::read_dir(&self.root).map_err(|e| {
+            ServiceError::ProcessMonitoring(format!("Failed to read {:?}: {}", self.root, e))
+        })?;
+
+        let mut processes = Vec::new();
+
+        for entry in entries.flatten() {
+            let pid = match entry.file_name().to_str().and_then(|s| s.parse::<u32>().ok()) {
+                Some(pid) if pid > 0 => pid,
+                _ => continue,
+            };
+
+            // Processes may exit while we are reading, so skip unreadable entries
+            if let Some(info) = self.read_process(pid) {
+                processes.push(info);
+            }
+        }
+
+        processes.sort_by_key(|p| p.pid);
+        Ok(processes)
+    }
+
+    fn read_process(&self, pid: u32) -> Option<ProcessInfo> {
+        let dir = self.root.join(pid.to_string());
+        let stat = fs::read_to_string(dir.join("stat")).ok()?;
+        let (stat_name, parent_pid) = Self::parse_stat(&stat)?;
+
+        // Prefer comm, which is not subject to the parsing ambiguity of stat
+        let name = fs::read_to_string(dir.join("comm"))
+            .map(|s| s.trim_end_matches('\n').to_string())
+            .unwrap_or(stat_name);
+
+        Some(ProcessInfo {
+            pid,
+            name,
+            parent_pid,
+        })
+    }
+
+    /// Parse the process name and parent PID out of a `/proc/<pid>/stat` line
+    fn parse_stat(stat: &str) -> Option<(String, u32)> {
+        // The name is wrapped in parentheses and may itself contain spaces or ')'
+        let open = stat.find('(')?;
+        let close = stat.rfind(')')?;
+        let name = stat.get(open + 1..close)?.to_string();
+
+        // Fields after the name: state, ppid, ...
+        let mut fields = stat.get(close + 1..)?.split_whitespace();
+        let _state = fields.next()?;
+        let parent_pid = fields.next()?.parse().ok()?;
+
+        Some((name, parent_pid))
+    }
+
+    /// Parse user plus system time, in clock ticks, out of a `/proc/<pid>/stat` line
+    fn parse_stat_ticks(stat: &str) -> Option<u64> {
+        // utime and stime are the 12th and 13th fields after the name
+        let close = stat.rfind(')')?;
+        let mut fields = stat.get(close + 1..)?.split_whitespace().skip(11);
+        let utime: u64 = fields.next()?.parse().ok()?;
+        let stime: u64 = fields.next()?.parse().ok()?;
+        Some(utime + stime)
+    }
+
+    /// Parse the start time, in clock ticks since boot, out of a `/proc/<pid>/stat` line
+    fn parse_stat_start(stat: &str) -> Option<u64> {
+        // starttime is the 20th field after the name
+        let close = stat.rfind(')')?;
+        stat.get(close + 1..)?.split_whitespace().nth(19)?.parse().ok()
+    }
+
+    /// Parse the effective UID out of the `Uid:` line of `/proc/<pid>/status`
+    fn parse_status_uid(status: &str) -> Option<u32> {
+        // Uid: real, effective, saved set, filesystem
+        let line = status.lines().find_map(|line| line.strip_prefix("Uid:"))?;
+        line.split_whitespace().nth(1)?.parse().ok()
+    }
+}
+
+/// Login name of a UID from the user database
+#[cfg(target_os = "linux")]
+fn user_name(uid: u32) -> Option<String> {
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buffer = vec![0 as libc::c_char; 4096];
+    let mut result = std::ptr::null_mut();
+
+    let status = unsafe { libc::getpwuid_r(uid, &mut passwd, buffer.as_mut_ptr(), buffer.len(), &mut result) };
+    if status != 0 || result.is_null() {
+        return None;
+    }
+
+    let name = unsafe { std::ffi::CStr::from_ptr(passwd.pw_name) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn user_name(_uid: u32) -> Option<String> {
+    None
+}
+
+/// Clock ticks per second that `/proc/<pid>/stat` times are counted in
+#[cfg(target_os = "linux")]
+fn clock_ticks() -> u64 {
+    match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        ticks if ticks > 0 => ticks as u64,
+        _ => 100,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn clock_ticks() -> u64 {
+    100
+}
+
+fn ticks_to_duration(ticks: u64) -> Duration {
+    let per_second = clock_ticks();
+    Duration::from_secs(ticks / per_second) + Duration::from_secs(ticks % per_second) / per_second as u32
+}
+
+impl Default for ProcFsSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessSource for ProcFsSource {
+    fn snapshot(&mut self) -> Result<Vec<ProcessInfo>, ServiceError> {
+        self.get_all_processes()
+    }
+
+    fn exe_path(&self, pid: u32) -> Option<PathBuf> {
+        let target = fs::read_link(self.root.join(pid.to_string()).join("exe")).ok()?;
+
+        // The kernel marks executables replaced or removed since exec
+        match target.to_str().and_then(|s| s.strip_suffix(" (deleted)")) {
+            Some(path) => Some(PathBuf::from(path)),
+            None => Some(target),
+        }
+    }
+
+    fn cmdline(&self, pid: u32) -> Option<String> {
+        let raw = fs::read(self.root.join(pid.to_string()).join("cmdline")).ok()?;
+
+        // Arguments are NUL-terminated; kernel threads have none
+        let args: Vec<String> = raw
+            .split(|&b| b == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect();
+
+        if args.is_empty() {
+            None
+        } else {
+            Some(args.join(" "))
+        }
+    }
+
+    fn owner(&self, pid: u32) -> Option<ProcessOwner> {
+        let status = fs::read_to_string(self.root.join(pid.to_string()).join("status")).ok()?;
+        let uid = Self::parse_status_uid(&status)?;
+
+        Some(ProcessOwner {
+            name: user_name(uid),
+            uid: Some(uid),
+        })
+    }
+
+    fn session(&self, pid: u32) -> Option<u32> {
+        let session = fs::read_to_string(self.root.join(pid.to_string()).join("sessionid")).ok()?;
+
+        // Processes outside any login session report (u32)-1
+        session.trim().parse().ok().filter(|&id| id != u32::MAX)
+    }
+
+    fn cpu_time(&self, pid: u32) -> Option<Duration> {
+        let stat = fs::read_to_string(self.root.join(pid.to_string()).join("stat")).ok()?;
+        Some(ticks_to_duration(Self::parse_stat_ticks(&stat)?))
+    }
+
+    fn start_time(&self, pid: u32) -> Option<Duration> {
+        let stat = fs::read_to_string(self.root.join(pid.to_string()).join("stat")).ok()?;
+        Some(ticks_to_duration(Self::parse_stat_start(&stat)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_process(root: &Path, pid: u32, stat: &str, comm: Option<&str>) {
+        let dir = root.join(pid.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("stat"), stat).unwrap();
+        if let Some(comm) = comm {
+            fs::write(dir.join("comm"), format!("{}\n", comm)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_parse_stat() {
+        let (name, ppid) = ProcFsSource::parse_stat("1234 (cargo) S 1 1234 1234 0 -1").unwrap();
+        assert_eq!(name, "cargo");
+        assert_eq!(ppid, 1);
+
+        // Names containing spaces and parentheses
+        let (name, ppid) = ProcFsSource::parse_stat("42 (my (odd) app) R 7 42 42 0").unwrap();
+        assert_eq!(name, "my (odd) app");
+        assert_eq!(ppid, 7);
+
+        assert!(ProcFsSource::parse_stat("garbage").is_none());
+    }
+
+    #[test]
+    fn test_cpu_time() {
+        let stat = "200 (my (odd) app) S 1 200 200 0 -1 4194560 9 0 0 0 250 75 0 0 20 0 1 0 99";
+        assert_eq!(ProcFsSource::parse_stat_ticks(stat), Some(325));
+        assert_eq!(ProcFsSource::parse_stat_ticks("200 (short) S 1 200"), None);
+
+        let root = tempfile::tempdir().unwrap();
+        write_process(root.path(), 200, stat, Some("app"));
+        let source = ProcFsSource::with_root(root.path());
+        assert_eq!(source.cpu_time(200), Some(Duration::from_millis(325 * 1000 / clock_ticks())));
+        assert_eq!(source.cpu_time(300), None);
+
+        assert_eq!(ProcFsSource::parse_stat_start(stat), Some(99));
+        assert_eq!(source.start_time(200), Some(Duration::from_millis(99 * 1000 / clock_ticks())));
+    }
+
+    #[test]
+    fn test_fixture_root() {
+        let root = tempfile::tempdir().unwrap();
+        write_process(root.path(), 1, "1 (systemd) S 0 1 1 0", Some("systemd"));
+        write_process(root.path(), 200, "200 (rustc) R 1 200 200 0", Some("rustc"));
+        write_process(root.path(), 300, "300 (no-comm) S 200 300 300 0", None);
+        fs::create_dir_all(root.path().join("self")).unwrap();
+        fs::write(root.path().join("uptime"), "1.0 1.0").unwrap();
+
+        let processes = ProcFsSource::with_root(root.path()).get_all_processes().unwrap();
+        assert_eq!(processes.len(), 3);
+        assert_eq!(processes[1].name, "rustc");
+        assert_eq!(processes[1].parent_pid, 1);
+        assert_eq!(processes[2].name, "no-comm");
+        assert_eq!(processes[2].parent_pid, 200);
+    }
+
+    #[test]
+    fn test_cmdline() {
+        let root = tempfile::tempdir().unwrap();
+        write_process(root.path(), 200, "200 (java) S 1 200 200 0", Some("java"));
+        write_process(root.path(), 2, "2 (kthreadd) S 0 0 0 0", Some("kthreadd"));
+        fs::write(
+            root.path().join("200/cmdline"),
+            b"java\0-Xmx2g\0org.gradle.launcher.daemon.bootstrap.GradleDaemon\0",
+        )
+        .unwrap();
+        fs::write(root.path().join("2/cmdline"), b"").unwrap();
+
+        let source = ProcFsSource::with_root(root.path());
+        assert_eq!(
+            source.cmdline(200).as_deref(),
+            Some("java -Xmx2g org.gradle.launcher.daemon.bootstrap.GradleDaemon")
+        );
+        assert_eq!(source.cmdline(2), None);
+        assert_eq!(source.cmdline(300), None);
+    }
+
+    #[test]
+    fn test_owner_and_session() {
+        let root = tempfile::tempdir().unwrap();
+        write_process(root.path(), 200, "200 (chrome) S 1 200 200 0", Some("chrome"));
+        write_process(root.path(), 2, "2 (kthreadd) S 0 0 0 0", Some("kthreadd"));
+        fs::write(
+            root.path().join("200/status"),
+            "Name:\tchrome\nUid:\t1001\t0\t0\t0\nGid:\t1001\t1001\t1001\t1001\n",
+        )
+        .unwrap();
+        fs::write(root.path().join("200/sessionid"), "3").unwrap();
+        fs::write(root.path().join("2/sessionid"), "4294967295").unwrap();
+
+        let source = ProcFsSource::with_root(root.path());
+        // The effective UID, which is what the process can access
+        let owner = source.owner(200).unwrap();
+        assert_eq!(owner.uid, Some(0));
+        #[cfg(target_os = "linux")]
+        assert_eq!(owner.name.as_deref(), Some("root"));
+
+        assert_eq!(source.session(200), Some(3));
+        assert_eq!(source.session(2), None);
+        assert_eq!(source.owner(2), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_exe_path() {
+        let source = ProcFsSource::new();
+        let path = source.exe_path(std::process::id()).unwrap();
+        assert_eq!(path, std::env::current_exe().unwrap());
+
+        assert!(source.exe_path(u32::MAX).is_none());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_get_processes() {
+        let processes = ProcFsSource::new().get_all_processes().unwrap();
+
+        let current_pid = std::process::id();
+        let found = processes.iter().any(|p| p.pid == current_pid);
+        assert!(found, "Current process not found in process list");
+    }
+}
@@ -0,0 +1,1319 @@
+use crate::config::settings::{Config, MatchMode, RuleConfig};
+use crate::cpu::{CoreInfo, CoreTarget};
+use crate::power::PowerState;
+use crate::process::view::ProcessView;
+use crate::process::pattern::{compile_regex_set, Pattern, PatternError, PatternSet};
+use crate::process::schedule::Schedule;
+use crate::process::source::ProcessOwner;
+use crate::process::usage::IdleDemotion;
+use crate::utils::error::{Result, ServiceError};
+use chrono::{DateTime, Utc};
+use regex::RegexSet;
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Name of the implicit rule built from `[whitelist]`
+pub const WHITELIST_RULE: &str = "whitelist";
+
+/// Process conditions mapped to the cores matching processes are pinned to
+///
+/// Every condition the rule has must match; each is a list of alternatives.
+/// Pattern lists carry the match mode of each entry: its prefix, or the
+/// rule's `match_mode` if it has none.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub name: String,
+    /// Higher priorities are checked first; equal priorities keep config order
+    pub priority: i32,
+    pub processes: PatternSet,
+    /// Globs over the full executable path; checked after `processes`
+    pub paths: PathGlobSet,
+    /// Patterns over the full command line; checked after `paths`
+    pub cmdline: PatternSet,
+    /// Patterns over the owning account; checked after `cmdline`
+    pub users: PatternSet,
+    /// Login sessions the process must run in; checked last
+    pub sessions: Vec<u32>,
+    /// Processes matching these patterns skip this rule and fall through to
+    /// the next one
+    pub exclude: PatternSet,
+    pub target: CoreTarget,
+    /// Replaces `target` on AC power
+    pub ac_target: Option<CoreTarget>,
+    /// Replaces `target` on battery power
+    pub battery_target: Option<CoreTarget>,
+    /// Move the process to `focus_target` while it is in the foreground
+    pub boost_on_focus: bool,
+    pub focus_target: CoreTarget,
+    /// Move processes that stay idle off `target` until they get busy again
+    pub idle_demotion: Option<IdleDemotion>,
+    pub single_numa_node: bool,
+    /// Descendants that match no rule themselves get this rule too
+    pub inherit_children: bool,
+    /// Deepest generation `inherit_children` reaches (1 = children only)
+    pub max_depth: Option<u32>,
+    /// Windows the rule applies in; outside them it is skipped
+    pub schedule: Option<Schedule>,
+}
+
+impl Rule {
+    /// Build a rule, compiling its patterns; fails on an invalid regex or
+    /// a `cmdline` condition without `processes`
+    pub fn from_config(index: usize, rule: &RuleConfig) -> Result<Self> {
+        let name = if rule.name.is_empty() {
+            format!("rule{}", index + 1)
+        } else {
+            rule.name.clone()
+        };
+        // Command lines are only read for processes whose name matched
+        if !rule.cmdline.is_empty() && rule.processes.is_empty() {
+            return Err(ServiceError::Config(format!(
+                "Rule {}: cmdline needs processes to narrow down which command lines are read",
+                name
+            )));
+        }
+        let match_mode = &rule.match_mode;
+        let compile = |entries: &[String]| {
+            PatternSet::parse(entries, match_mode).map_err(|e| ServiceError::Config(format!("Rule {}: {}", name, e)))
+        };
+
+        Ok(Self {
+            processes: compile(&rule.processes)?,
+            paths: PathGlobSet::new(&rule.paths).map_err(|e| ServiceError::Config(format!("Rule {}: {}", name, e)))?,
+            cmdline: compile(&rule.cmdline)?,
+            users: compile(&rule.users)?,
+            sessions: sessions(&rule.sessions).map_err(|e| ServiceError::Config(format!("Rule {}: {}", name, e)))?,
+            exclude: compile(&rule.exclude)?,
+            target: rule.target.clone(),
+            ac_target: rule.ac_target.clone(),
+            battery_target: rule.battery_target.clone(),
+            boost_on_focus: rule.boost_on_focus,
+            focus_target: rule.focus_target.clone(),
+            idle_demotion: rule
+                .idle_demotion
+                .as_ref()
+                .map(IdleDemotion::from_config)
+                .transpose()
+                .map_err(|e| ServiceError::Config(format!("Rule {}: {}", name, e)))?,
+            single_numa_node: rule.single_numa_node,
+            inherit_children: rule.inherit_children,
+            max_depth: rule.max_depth,
+            schedule: Schedule::from_config(&rule.schedule)
+                .map_err(|e| ServiceError::Config(format!("Rule {}: {}", name, e)))?,
+            priority: rule.priority,
+            name,
+        })
+    }
+
+    /// The `[whitelist]` section as a rule, or `None` if it lists no processes
+    pub fn from_whitelist(config: &Config) -> Result<Option<Self>> {
+        let whitelist = &config.whitelist;
+        if whitelist.processes.is_empty() {
+            return Ok(None);
+        }
+
+        let match_mode = &whitelist.match_mode;
+        let processes = PatternSet::parse(&whitelist.processes, match_mode)
+            .map_err(|e| ServiceError::Config(format!("Rule {}: {}", WHITELIST_RULE, e)))?;
+
+        Ok(Some(Self {
+            name: WHITELIST_RULE.to_string(),
+            priority: 0,
+            processes,
+            paths: PathGlobSet::default(),
+            cmdline: PatternSet::default(),
+            users: PatternSet::default(),
+            sessions: Vec::new(),
+            exclude: PatternSet::default(),
+            target: whitelist.target.clone(),
+            ac_target: whitelist.ac_target.clone(),
+            battery_target: whitelist.battery_target.clone(),
+            boost_on_focus: whitelist.boost_on_focus,
+            focus_target: whitelist.focus_target.clone(),
+            idle_demotion: whitelist
+                .idle_demotion
+                .as_ref()
+                .map(IdleDemotion::from_config)
+                .transpose()
+                .map_err(|e| ServiceError::Config(format!("Rule {}: {}", WHITELIST_RULE, e)))?,
+            single_numa_node: whitelist.single_numa_node,
+            inherit_children: false,
+            max_depth: None,
+            schedule: None,
+        }))
+    }
+
+    /// A rule without any conditions never matches
+    pub fn has_conditions(&self) -> bool {
+        !self.processes.is_empty()
+            || !self.paths.is_empty()
+            || !self.cmdline.is_empty()
+            || !self.users.is_empty()
+            || !self.sessions.is_empty()
+    }
+
+    /// Whether a process `depth` generations below a matching one inherits
+    /// this rule
+    pub fn inherited_at(&self, depth: u32) -> bool {
+        self.inherit_children && self.max_depth.is_none_or(|max_depth| depth <= max_depth)
+    }
+
+    /// Whether the rule applies at `now`; rules without a schedule always do
+    pub fn in_schedule(&self, now: DateTime<Utc>) -> bool {
+        self.schedule.as_ref().is_none_or(|schedule| schedule.is_open(now))
+    }
+
+    /// The target that applies on the given power source
+    pub fn target_for(&self, power_state: PowerState) -> &CoreTarget {
+        let target = match power_state {
+            PowerState::Ac => self.ac_target.as_ref(),
+            PowerState::Battery => self.battery_target.as_ref(),
+        };
+        target.unwrap_or(&self.target)
+    }
+
+    /// Every target the rule can pin to, with the name of its setting
+    pub fn targets(&self) -> impl Iterator<Item = (&'static str, &CoreTarget)> {
+        [
+            ("target", Some(&self.target)),
+            ("ac_target", self.ac_target.as_ref()),
+            ("battery_target", self.battery_target.as_ref()),
+            ("focus_target", self.boost_on_focus.then_some(&self.focus_target)),
+            ("idle_demotion.target", self.idle_demotion.as_ref().map(|idle| &idle.target)),
+        ]
+        .into_iter()
+        .filter_map(|(field, target)| Some((field, target?)))
+    }
+
+    /// Whether the rule's target depends on the power source
+    pub fn has_power_targets(&self) -> bool {
+        self.ac_target.is_some() || self.battery_target.is_some()
+    }
+
+    /// Whether the conditions match, regardless of the schedule
+    pub fn matches(&self, process: &ProcessView) -> bool {
+        self.has_conditions()
+            && Attribute::ALL
+                .iter()
+                .all(|&attribute| !matches!(self.check(attribute, process, &mut |_| {}), Check::Failed))
+            && self.excluding_pattern(process.name()).is_none()
+    }
+
+    /// Check one condition, calling `failed` with each pattern that did not
+    /// match; the attribute is only fetched if the rule has the condition
+    fn check(&self, attribute: Attribute, process: &ProcessView, failed: &mut dyn FnMut(&str)) -> Check {
+        match attribute {
+            Attribute::Name => check_set(&self.processes, || vec![Cow::Borrowed(process.name())], failed),
+            Attribute::Path => first_match(
+                self.paths.globs().iter().map(|glob| Cow::Borrowed(glob.as_str())),
+                || process.exe_path().and_then(|path| self.paths.first_match(path)),
+                failed,
+            ),
+            Attribute::Cmdline => check_set(&self.cmdline, || process.cmdline().map(Cow::Borrowed).into_iter().collect(), failed),
+            Attribute::User => check_set(&self.users, || process.owner().map(owner_values).unwrap_or_default(), failed),
+            Attribute::Session => first_match(
+                self.sessions.iter().map(|session| Cow::Owned(session.to_string())),
+                || process.session().and_then(|session| self.sessions.iter().position(|&s| s == session)),
+                failed,
+            ),
+        }
+    }
+
+    fn excluding_pattern(&self, process_name: &str) -> Option<&str> {
+        self.exclude.matching(process_name).map(Pattern::as_str)
+    }
+}
+
+/// Result of checking one condition of a rule
+enum Check {
+    /// The rule does not have this condition
+    Unset,
+    Matched(String),
+    Failed,
+}
+
+/// Check a condition whose entries are `labels`; `position` finds the first
+/// matching entry and is only called if there are any. `failed` gets the
+/// entries before it.
+fn first_match<'l>(
+    labels: impl ExactSizeIterator<Item = Cow<'l, str>>,
+    position: impl FnOnce() -> Option<usize>,
+    failed: &mut dyn FnMut(&str),
+) -> Check {
+    if labels.len() == 0 {
+        return Check::Unset;
+    }
+
+    let first = position();
+    for (index, label) in labels.enumerate() {
+        if Some(index) == first {
+            return Check::Matched(label.into_owned());
+        }
+        failed(&label);
+    }
+    Check::Failed
+}
+
+/// Like `first_match`, for a pattern list checked against one or more values
+/// of an attribute; `values` is only called if the list is not empty
+fn check_set<'v>(set: &PatternSet, values: impl FnOnce() -> Vec<Cow<'v, str>>, failed: &mut dyn FnMut(&str)) -> Check {
+    first_match(
+        set.patterns().iter().map(|pattern| Cow::Borrowed(pattern.as_str())),
+        || values().iter().filter_map(|value| set.first_match(value)).min(),
+        failed,
+    )
+}
+
+/// The sessions of a rule, rejecting one listed twice
+fn sessions(sessions: &[u32]) -> std::result::Result<Vec<u32>, String> {
+    let mut seen = HashSet::new();
+    match sessions.iter().find(|&&session| !seen.insert(session)) {
+        Some(session) => Err(format!("session {} is listed twice", session)),
+        None => Ok(sessions.to_vec()),
+    }
+}
+
+/// Globs over a full executable path, compiled together at load
+///
+/// `*` and `?` stay within one directory and `**` spans any number of them.
+/// Matching ignores case and treats `\` and `/` alike.
+#[derive(Debug, Clone, Default)]
+pub struct PathGlobSet {
+    globs: Vec<String>,
+    regexes: Option<RegexSet>,
+}
+
+impl PathGlobSet {
+    /// Compile the globs; fails only if they are too large to compile
+    pub fn new(globs: &[String]) -> std::result::Result<Self, PatternError> {
+        let sources: Vec<String> = globs.iter().map(|glob| glob_to_regex(glob)).collect();
+        let regexes: Vec<(&str, &str)> =
+            sources.iter().zip(globs).map(|(source, glob)| (source.as_str(), glob.as_str())).collect();
+        let regexes = if regexes.is_empty() { None } else { Some(compile_regex_set(&regexes)?) };
+
+        Ok(Self {
+            globs: globs.to_vec(),
+            regexes,
+        })
+    }
+
+    pub fn globs(&self) -> &[String] {
+        &self.globs
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.globs.is_empty()
+    }
+
+    /// Position of the first glob matching `path`, in list order
+    pub fn first_match(&self, path: &Path) -> Option<usize> {
+        let path = path.to_string_lossy().replace('\\', "/");
+        self.regexes.as_ref()?.matches(&path).iter().next()
+    }
+}
+
+fn glob_to_regex(pattern: &str) -> String {
+    let normalized = pattern.replace('\\', "/");
+    let mut regex = String::from("(?i)^");
+    let mut chars = normalized.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` also matches no directory at all
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+
+    regex.push('$');
+    regex
+}
+
+/// All rules in match order: by descending priority, then `[[rules]]` as
+/// written, then the whitelist
+pub fn build_rules(config: &Config) -> Result<Vec<Rule>> {
+    let mut rules: Vec<Rule> = config
+        .rules
+        .iter()
+        .enumerate()
+        .map(|(index, rule)| Rule::from_config(index, rule))
+        .collect::<Result<_>>()?;
+    rules.extend(Rule::from_whitelist(config)?);
+
+    // Stable, so equal priorities keep their order
+    rules.sort_by_key(|rule| Reverse(rule.priority));
+    Ok(rules)
+}
+
+/// What rule evaluation decided for a process
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatchOutcome {
+    /// A rule matched; `rule_index` is the position in match order and
+    /// `conditions` the pattern that matched for each condition of the rule
+    Matched {
+        rule_index: usize,
+        rule_name: String,
+        conditions: Vec<(Attribute, String)>,
+        /// The rule's schedule, open at the time, if it has one
+        schedule: Option<String>,
+        target: CoreTarget,
+    },
+    /// The process is on the global exclude list and no rule was checked
+    Excluded { pattern: String },
+    /// No rule applies, but the conditions of a scheduled rule matched
+    /// while it was outside its schedule; the first such rule
+    OutsideSchedule {
+        rule_index: usize,
+        rule_name: String,
+        schedule: String,
+    },
+    NoMatch,
+}
+
+/// Which process attribute a pattern was checked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attribute {
+    Name,
+    Path,
+    Cmdline,
+    User,
+    Session,
+}
+
+impl Attribute {
+    /// Every process attribute in evaluation order, cheapest first
+    pub const ALL: [Attribute; 5] = [
+        Attribute::Name,
+        Attribute::Path,
+        Attribute::Cmdline,
+        Attribute::User,
+        Attribute::Session,
+    ];
+}
+
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Attribute::Name => write!(f, "pattern"),
+            Attribute::Path => write!(f, "path"),
+            Attribute::Cmdline => write!(f, "cmdline"),
+            Attribute::User => write!(f, "user"),
+            Attribute::Session => write!(f, "session"),
+        }
+    }
+}
+
+/// A pattern checked before the deciding one
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternAttempt {
+    pub rule_index: usize,
+    pub rule_name: String,
+    pub attribute: Attribute,
+    pub pattern: String,
+    /// Set when the pattern matched but the rule's `exclude` list skipped it
+    pub excluded_by: Option<String>,
+    /// Set when the rule matched but was outside this schedule
+    pub outside_schedule: Option<String>,
+}
+
+/// The outcome of rule evaluation and the patterns tried on the way
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchDecision {
+    pub process_name: String,
+    pub outcome: MatchOutcome,
+    /// Patterns tried before the outcome, in order; only filled by `explain`
+    pub tried: Vec<PatternAttempt>,
+}
+
+impl MatchDecision {
+    /// Position of the matching rule in match order
+    pub fn rule_index(&self) -> Option<usize> {
+        match &self.outcome {
+            MatchOutcome::Matched { rule_index, .. } => Some(*rule_index),
+            _ => None,
+        }
+    }
+
+    /// The pattern that decided the outcome; for rules with several
+    /// conditions, the one checked first
+    pub fn pattern(&self) -> Option<&str> {
+        match &self.outcome {
+            MatchOutcome::Matched { conditions, .. } => conditions.first().map(|(_, pattern)| pattern.as_str()),
+            MatchOutcome::Excluded { pattern } => Some(pattern),
+            MatchOutcome::OutsideSchedule { .. } | MatchOutcome::NoMatch => None,
+        }
+    }
+
+    fn record(&mut self, rule_index: usize, rule: &Rule, attribute: Attribute, pattern: &str) -> &mut PatternAttempt {
+        self.tried.push(PatternAttempt {
+            rule_index,
+            rule_name: rule.name.clone(),
+            attribute,
+            pattern: pattern.to_string(),
+            excluded_by: None,
+            outside_schedule: None,
+        });
+        self.tried.last_mut().unwrap()
+    }
+}
+
+impl fmt::Display for MatchDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.outcome {
+            MatchOutcome::Matched {
+                rule_index,
+                rule_name,
+                conditions,
+                schedule,
+                target,
+            } => {
+                let conditions: Vec<String> = conditions
+                    .iter()
+                    .map(|(attribute, pattern)| format!("{} \"{}\"", attribute, pattern))
+                    .collect();
+                write!(
+                    f,
+                    "{}: rule #{} \"{}\" matched {}",
+                    self.process_name,
+                    rule_index,
+                    rule_name,
+                    conditions.join(" and ")
+                )?;
+                if let Some(schedule) = schedule {
+                    write!(f, " within schedule \"{}\"", schedule)?;
+                }
+                write!(f, " -> {}", target)?
+            }
+            MatchOutcome::Excluded { pattern } => write!(
+                f,
+                "{}: excluded by exclude_processes entry \"{}\"",
+                self.process_name, pattern
+            )?,
+            MatchOutcome::OutsideSchedule {
+                rule_index,
+                rule_name,
+                schedule,
+            } => write!(
+                f,
+                "{}: only rule #{} \"{}\" matched, but it is outside its schedule \"{}\"",
+                self.process_name, rule_index, rule_name, schedule
+            )?,
+            MatchOutcome::NoMatch => write!(f, "{}: no rule matched", self.process_name)?,
+        }
+
+        for attempt in &self.tried {
+            write!(
+                f,
+                "\n  tried rule #{} \"{}\" {} \"{}\": ",
+                attempt.rule_index, attempt.rule_name, attempt.attribute, attempt.pattern
+            )?;
+            match (&attempt.excluded_by, &attempt.outside_schedule) {
+                (Some(exclude), _) => write!(f, "matched, but skipped by rule exclude \"{}\"", exclude)?,
+                (None, Some(schedule)) => write!(f, "matched, but outside the schedule \"{}\"", schedule)?,
+                (None, None) => write!(f, "no match")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Rules in match order together with the global exclude list
+pub struct RuleSet {
+    rules: Vec<Rule>,
+    /// `exclude_processes` entries, compiled
+    exclusions: PatternSet,
+    /// Match exclusions as substrings, the pre-pattern behaviour
+    substring_exclusions: bool,
+    /// Substring-only exclusion hits already warned about, as (entry, process)
+    warned: Mutex<HashSet<(String, String)>>,
+    /// Whether each rule is inside its schedule, as of `update_schedules`
+    active: Vec<bool>,
+    /// Picks between each rule's AC and battery targets
+    power_state: PowerState,
+}
+
+impl RuleSet {
+    /// Build the rules and exclusions; fails on an invalid regex
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let rules = build_rules(config)?;
+        for rule in rules.iter().filter(|rule| !rule.has_conditions()) {
+            log::warn!("Rule {} has no conditions and will never match", rule.name);
+        }
+
+        let whitelist = &config.whitelist;
+        let match_mode = &whitelist.match_mode;
+        if whitelist.substring_exclusions {
+            log::warn!(
+                "substring_exclusions is on: exclude_processes entries match any process name containing them"
+            );
+        }
+
+        let exclusions = PatternSet::parse_anchored(&whitelist.exclude_processes, match_mode)
+            .map_err(|e| ServiceError::Config(format!("exclude_processes: {}", e)))?;
+
+        let plain = plain_entries(&exclusions);
+        if !whitelist.substring_exclusions && !plain.is_empty() {
+            log::warn!(
+                "exclude_processes entries {:?} now exclude only processes with exactly that name; older versions \
+                 excluded every name containing them. Prefix them with \"exact:\" to keep the new meaning and \
+                 silence this warning, or set substring_exclusions = true for the old one",
+                plain
+            );
+        }
+
+        // Scheduled rules stay off until the first `update_schedules`
+        let active = rules.iter().map(|rule| rule.schedule.is_none()).collect();
+
+        Ok(Self {
+            rules,
+            exclusions,
+            substring_exclusions: whitelist.substring_exclusions,
+            warned: Mutex::new(HashSet::new()),
+            active,
+            power_state: PowerState::default(),
+        })
+    }
+
+    /// Fail on targets that select no processors on `core_info`, such as
+    /// `ccd1` on a single-CCD part or `e_cores` on a CPU without E-cores
+    pub fn check_targets(&self, core_info: &CoreInfo) -> Result<()> {
+        for rule in &self.rules {
+            for (field, target) in rule.targets() {
+                if core_info.can_resolve(target) {
+                    continue;
+                }
+
+                let mut named = target;
+                while let CoreTarget::Physical(inner) = named {
+                    named = inner;
+                }
+                let reason = match named {
+                    CoreTarget::Named(name) if core_info.named_set(name).is_none() => {
+                        let known: Vec<&str> = core_info.named_sets().map(|(name, _)| name).collect();
+                        format!(
+                            "is not a core set on this machine (detected: {})",
+                            if known.is_empty() { "none".to_string() } else { known.join(", ") }
+                        )
+                    }
+                    _ => "selects no processors on this machine".to_string(),
+                };
+                return Err(ServiceError::Config(format!("Rule {}: {} {} {}", rule.name, field, target, reason)));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn get(&self, rule_index: usize) -> Option<&Rule> {
+        self.rules.get(rule_index)
+    }
+
+    /// Whether a rule is inside its schedule
+    pub fn is_active(&self, rule_index: usize) -> bool {
+        self.active.get(rule_index).copied().unwrap_or(false)
+    }
+
+    pub fn power_state(&self) -> PowerState {
+        self.power_state
+    }
+
+    /// Whether any rule's target depends on the power source
+    pub fn has_power_targets(&self) -> bool {
+        self.rules.iter().any(Rule::has_power_targets)
+    }
+
+    /// Switch the targets rules resolve to; returns whether the state changed
+    pub fn set_power_state(&mut self, power_state: PowerState) -> bool {
+        let changed = self.power_state != power_state;
+        self.power_state = power_state;
+        changed
+    }
+
+    /// Bring the schedule state of every rule up to `now`, returning the
+    /// rules whose window opened or closed
+    pub fn update_schedules(&mut self, now: DateTime<Utc>) -> Vec<usize> {
+        let mut changed = Vec::new();
+        for (rule_index, rule) in self.rules.iter().enumerate() {
+            let active = rule.in_schedule(now);
+            if self.active[rule_index] != active {
+                self.active[rule_index] = active;
+                changed.push(rule_index);
+            }
+        }
+        changed
+    }
+
+    /// Decide which rule applies to a process; the first match wins
+    pub fn evaluate(&self, process: &ProcessView) -> MatchDecision {
+        self.decide(process, false)
+    }
+
+    /// Like `evaluate`, but also records every pattern tried before the outcome
+    pub fn explain(&self, process: &ProcessView) -> MatchDecision {
+        self.decide(process, true)
+    }
+
+    fn decide(&self, process: &ProcessView, record: bool) -> MatchDecision {
+        let process_name = process.name();
+        let mut decision = MatchDecision {
+            process_name: process_name.to_string(),
+            outcome: MatchOutcome::NoMatch,
+            tried: Vec::new(),
+        };
+
+        if let Some(pattern) = self.excluding_pattern(process_name) {
+            decision.outcome = MatchOutcome::Excluded {
+                pattern: pattern.to_string(),
+            };
+            return decision;
+        }
+
+        for (rule_index, rule) in self.rules.iter().enumerate() {
+            // In order, so the lazily fetched attributes are only looked up
+            // for processes that passed the conditions before them
+            let mut conditions = Vec::new();
+            let mut failed = false;
+            for attribute in Attribute::ALL {
+                let mut tried = |pattern: &str| {
+                    if record {
+                        decision.record(rule_index, rule, attribute, pattern);
+                    }
+                };
+                match rule.check(attribute, process, &mut tried) {
+                    Check::Unset => {}
+                    Check::Matched(pattern) => conditions.push((attribute, pattern)),
+                    Check::Failed => {
+                        failed = true;
+                        break;
+                    }
+                }
+            }
+            if failed || conditions.is_empty() {
+                continue;
+            }
+
+            let schedule = rule.schedule.as_ref().map(ToString::to_string);
+            if let Some(schedule) = schedule.as_ref().filter(|_| !self.is_active(rule_index)) {
+                if record {
+                    let (attribute, pattern) = &conditions[0];
+                    decision.record(rule_index, rule, *attribute, pattern).outside_schedule = Some(schedule.clone());
+                }
+                if decision.outcome == MatchOutcome::NoMatch {
+                    decision.outcome = MatchOutcome::OutsideSchedule {
+                        rule_index,
+                        rule_name: rule.name.clone(),
+                        schedule: schedule.clone(),
+                    };
+                }
+                continue;
+            }
+
+            // The rule's exclusions apply to all of its patterns
+            if let Some(exclude) = rule.excluding_pattern(process_name) {
+                if record {
+                    let (attribute, pattern) = &conditions[0];
+                    decision.record(rule_index, rule, *attribute, pattern).excluded_by = Some(exclude.to_string());
+                }
+                continue;
+            }
+
+            decision.outcome = MatchOutcome::Matched {
+                rule_index,
+                rule_name: rule.name.clone(),
+                conditions,
+                schedule,
+                target: rule.target_for(self.power_state).clone(),
+            };
+            return decision;
+        }
+
+        decision
+    }
+
+    fn excluding_pattern(&self, process_name: &str) -> Option<&str> {
+        if self.substring_exclusions {
+            return self.excluding_substring(process_name);
+        }
+
+        self.exclusions.matching(process_name).map(Pattern::as_str)
+    }
+
+    fn excluding_substring(&self, process_name: &str) -> Option<&str> {
+        // Entries with a prefix are new-style patterns and keep their matcher
+        let name_lower = process_name.to_lowercase();
+        let patterns = self.exclusions.patterns();
+        let index = (0..patterns.len()).find(|&index| {
+            if patterns[index].has_prefix() {
+                self.exclusions.matches_at(index, process_name)
+            } else {
+                name_lower.contains(&patterns[index].text.to_lowercase())
+            }
+        })?;
+        let entry = patterns[index].as_str();
+
+        // Warn once per process name about hits the pattern would not make,
+        // such as "system" excluding systemsettings.exe
+        if !self.exclusions.matches_at(index, process_name) {
+            let key = (entry.to_string(), name_lower);
+            if self.warned.lock().unwrap().insert(key) {
+                log::warn!(
+                    "Exclusion \"{}\" matches {} only as a substring; turn off substring_exclusions and use patterns such as \"glob:*{}*\" if this is intended",
+                    entry,
+                    process_name,
+                    entry
+                );
+            }
+        }
+
+        Some(entry)
+    }
+}
+
+/// Entries without a prefix or any wildcard or regex syntax, such as
+/// `system`, which older versions matched as substrings
+fn plain_entries(patterns: &PatternSet) -> Vec<&str> {
+    patterns
+        .patterns()
+        .iter()
+        .filter(|pattern| !pattern.has_prefix())
+        .filter(|pattern| match pattern.mode {
+            MatchMode::Exact => true,
+            MatchMode::Wildcard => !pattern.text.contains(['*', '?']),
+            MatchMode::Regex => regex::escape(&pattern.text) == pattern.text,
+        })
+        .map(Pattern::as_str)
+        .collect()
+}
+
+/// The values a user pattern is checked against: the owner's full name, its
+/// name without the domain, and its UID
+fn owner_values(owner: &ProcessOwner) -> Vec<Cow<'_, str>> {
+    [owner.name.as_deref(), owner.user_name()]
+        .into_iter()
+        .flatten()
+        .map(Cow::Borrowed)
+        .chain(owner.uid.map(|uid| Cow::Owned(uid.to_string())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn create_test_config() -> Config {
+        let mut config = Config::default();
+        config.whitelist.processes = vec!["test.exe".to_string(), "*.game.exe".to_string()];
+        config
+    }
+
+    fn view(process_name: &str) -> ProcessView<'static> {
+        ProcessView::from_name(process_name)
+    }
+
+    fn with_rules(mut config: Config, rules: &str) -> Config {
+        config.rules = toml::from_str::<Config>(rules).unwrap().rules;
+        config
+    }
+
+    #[test]
+    fn test_rules_come_before_the_whitelist() {
+        let config = with_rules(
+            create_test_config(),
+            r#"
+            [[rules]]
+            name = "encoders"
+            processes = ["ffmpeg.exe", "handbrake*.exe"]
+            target = "e_cores"
+
+            [[rules]]
+            match_mode = "regex"
+            processes = ["^backup"]
+            target = "0-1"
+            "#,
+        );
+
+        let rules = build_rules(&config).unwrap();
+        let names: Vec<&str> = rules.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["encoders", "rule2", WHITELIST_RULE]);
+
+        assert!(rules[0].matches(&view("HandBrakeCLI.exe")));
+        assert_eq!(rules[0].target, CoreTarget::ECores);
+        assert!(rules[1].matches(&view("backupagent.exe")));
+        assert!(!rules[1].matches(&view("mybackup.exe")));
+        assert!(rules[2].matches(&view("test.exe")));
+    }
+
+    #[test]
+    fn test_empty_whitelist_adds_no_rule() {
+        let config = Config::default();
+        assert!(build_rules(&config).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_priority_and_rule_exclusions() {
+        let config = with_rules(
+            create_test_config(),
+            r#"
+            [[rules]]
+            name = "background"
+            processes = ["code*.exe", "*.exe"]
+            exclude = ["code.exe"]
+            target = "e_cores"
+
+            [[rules]]
+            name = "editors"
+            priority = -1
+            processes = ["code.exe"]
+            target = "p_cores"
+            "#,
+        );
+        let rules = RuleSet::from_config(&config).unwrap();
+        let names: Vec<&str> = rules.rules().iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["background", WHITELIST_RULE, "editors"]);
+
+        // "background" matches but its exclusion skips it, so "editors" decides
+        let decision = rules.explain(&view("Code.exe"));
+        assert_eq!(decision.rule_index(), Some(2));
+        assert_eq!(decision.pattern(), Some("code.exe"));
+        assert_eq!(decision.tried.len(), 3);
+        assert_eq!(decision.tried[0].excluded_by.as_deref(), Some("code.exe"));
+        assert_eq!(decision.tried[1].pattern, "test.exe");
+
+        assert_eq!(rules.evaluate(&view("Code.exe")).rule_index(), Some(2));
+        assert!(rules.evaluate(&view("Code.exe")).tried.is_empty());
+        assert_eq!(rules.evaluate(&view("notepad.exe")).rule_index(), Some(0));
+    }
+
+    #[test]
+    fn test_path_globs() {
+        let globs = |globs: &[&str]| {
+            let globs: Vec<String> = globs.iter().map(|glob| glob.to_string()).collect();
+            PathGlobSet::new(&globs).unwrap()
+        };
+        let matches = |glob: &str, path: &str| globs(&[glob]).first_match(Path::new(path)).is_some();
+        assert!(matches(r"D:\Games\**\*.exe", r"D:\Games\Doom\bin\doom.exe"));
+        assert!(matches(r"D:\Games\**\*.exe", "d:/games/solitaire.EXE"));
+        assert!(!matches(r"D:\Games\**\*.exe", r"D:\Games\readme.txt"));
+        assert!(!matches(r"D:\Games\**\*.exe", r"C:\D\Games\doom.exe"));
+
+        assert!(matches("/usr/bin/*", "/usr/bin/ffmpeg"));
+        assert!(!matches("/usr/bin/*", "/usr/bin/x/ffmpeg"));
+        assert!(matches("/opt/app?/[x]", "/opt/app1/[x]"));
+
+        // The first glob in list order wins
+        let set = globs(&["/opt/**/bin/*", "/opt/**", "/usr/**"]);
+        assert_eq!(set.first_match(Path::new("/opt/app/readme")), Some(1));
+        assert_eq!(set.first_match(Path::new("/opt/app/bin/app")), Some(0));
+        assert_eq!(set.first_match(Path::new("/home/app")), None);
+        assert_eq!(PathGlobSet::default().first_match(Path::new("/opt/app")), None);
+
+        // Too large to compile: a config error rather than a panic
+        let rules = format!("[[rules]]\nname = \"huge\"\npaths = [\"{}\"]\n", "?".repeat(100_000));
+        let config = with_rules(create_test_config(), &rules);
+        let error = RuleSet::from_config(&config).err().unwrap().to_string();
+        assert!(error.starts_with("Configuration error: Rule huge: invalid"), "{}", error);
+    }
+
+    #[test]
+    fn test_path_rules() {
+        let config = with_rules(
+            create_test_config(),
+            r#"
+            [[rules]]
+            name = "steam"
+            processes = ["*.exe"]
+            paths = ['C:\Program Files (x86)\Steam\**']
+            target = "e_cores"
+
+            [[rules]]
+            name = "games"
+            paths = ['D:\Games\**\*.exe']
+            target = "p_cores"
+
+            [[rules]]
+            name = "empty"
+            target = "all_cores"
+            "#,
+        );
+        let rules = RuleSet::from_config(&config).unwrap();
+
+        let doom = view("doom.exe").with_exe_path(r"D:\Games\id\doom.exe");
+        assert_eq!(rules.evaluate(&doom).rule_index(), Some(1));
+        assert_eq!(rules.evaluate(&doom).pattern(), Some(r"D:\Games\**\*.exe"));
+        assert!(rules.rules()[1].matches(&doom));
+
+        let steam = view("steamwebhelper.exe").with_exe_path(r"C:\Program Files (x86)\Steam\bin\steamwebhelper.exe");
+        assert_eq!(rules.evaluate(&steam).rule_index(), Some(0));
+
+        // Without a path only the name-only rules can match
+        assert_eq!(rules.evaluate(&view("test.exe")).rule_index(), Some(3));
+        assert!(!rules.rules()[2].matches(&view("anything.exe")));
+
+        let decision = rules.explain(&doom);
+        assert_eq!(
+            decision.to_string(),
+            "doom.exe: rule #1 \"games\" matched path \"D:\\Games\\**\\*.exe\" -> p_cores\n  \
+             tried rule #0 \"steam\" path \"C:\\Program Files (x86)\\Steam\\**\": no match"
+        );
+    }
+
+    #[test]
+    fn test_cmdline_rules() {
+        let config = with_rules(
+            create_test_config(),
+            r#"
+            [[rules]]
+            name = "gradle"
+            processes = ["java.exe"]
+            cmdline = ["*GradleDaemon*"]
+            target = "p_cores"
+
+            [[rules]]
+            name = "jvm"
+            match_mode = "regex"
+            processes = ["^javaw?\\.exe$"]
+            cmdline = ["-Xmx\\d+g"]
+            target = "e_cores"
+            "#,
+        );
+        let rules = RuleSet::from_config(&config).unwrap();
+
+        let daemon = view("java.exe").with_cmdline("java -Xmx2g org.gradle.launcher.daemon.bootstrap.GradleDaemon");
+        let decision = rules.explain(&daemon);
+        assert_eq!(decision.rule_index(), Some(0));
+        assert_eq!(
+            decision.to_string(),
+            "java.exe: rule #0 \"gradle\" matched pattern \"java.exe\" and cmdline \"*GradleDaemon*\" -> p_cores"
+        );
+
+        let ide = view("java.exe").with_cmdline("java -Xmx8g com.intellij.idea.Main");
+        let decision = rules.explain(&ide);
+        assert_eq!(decision.rule_index(), Some(1));
+        assert_eq!(decision.tried[0].attribute, Attribute::Cmdline);
+
+        // An unknown command line matches no cmdline pattern
+        assert_eq!(rules.evaluate(&view("java.exe")).outcome, MatchOutcome::NoMatch);
+
+        // A command line alone would have to be read for every process
+        let config = with_rules(
+            create_test_config(),
+            r#"
+            [[rules]]
+            name = "any-jvm"
+            cmdline = ["*-Xmx*"]
+            "#,
+        );
+        let error = RuleSet::from_config(&config).err().unwrap().to_string();
+        assert!(error.contains("Rule any-jvm: cmdline needs processes"), "{}", error);
+    }
+
+    #[test]
+    fn test_user_and_session_rules() {
+        let config = with_rules(
+            create_test_config(),
+            r#"
+            [[rules]]
+            name = "kiosk"
+            processes = ["chrome.exe"]
+            users = ["kiosk"]
+            target = "e_cores"
+
+            [[rules]]
+            name = "console"
+            match_mode = "exact"
+            processes = ["chrome.exe"]
+            users = ["1000"]
+            sessions = [1]
+            target = "p_cores"
+            "#,
+        );
+        let rules = RuleSet::from_config(&config).unwrap();
+        let owner = |name: Option<&str>, uid: Option<u32>| ProcessOwner {
+            name: name.map(str::to_string),
+            uid,
+        };
+
+        // Users match with or without the domain
+        let kiosk = view("chrome.exe").with_owner(owner(Some("KIOSK-PC\\kiosk"), None));
+        assert_eq!(rules.evaluate(&kiosk).rule_index(), Some(0));
+
+        let console = view("chrome.exe").with_owner(owner(None, Some(1000))).with_session(1);
+        let decision = rules.explain(&console);
+        assert_eq!(
+            decision.to_string(),
+            "chrome.exe: rule #1 \"console\" matched pattern \"chrome.exe\" and user \"1000\" and session \"1\" -> p_cores\n  \
+             tried rule #0 \"kiosk\" user \"kiosk\": no match"
+        );
+
+        let remote = view("chrome.exe").with_owner(owner(None, Some(1000))).with_session(2);
+        assert_eq!(rules.evaluate(&remote).outcome, MatchOutcome::NoMatch);
+        assert!(!rules.rules()[1].matches(&remote));
+
+        let rules = "[[rules]]\nname = \"twice\"\nsessions = [1, 2, 1]\n";
+        let error = RuleSet::from_config(&with_rules(create_test_config(), rules)).err().unwrap();
+        assert_eq!(error.to_string(), "Configuration error: Rule twice: session 1 is listed twice");
+    }
+
+    #[test]
+    fn test_exclusion_patterns() {
+        let mut config = create_test_config();
+        config.whitelist.processes = vec!["*.exe".to_string(), "System".to_string()];
+        config.whitelist.exclude_processes = vec![
+            "system".to_string(),
+            "re:^svc".to_string(),
+            "exact:code*.exe".to_string(),
+        ];
+        let rules = RuleSet::from_config(&config).unwrap();
+
+        let excluded = |name: &str| matches!(rules.evaluate(&view(name)).outcome, MatchOutcome::Excluded { .. });
+        assert!(excluded("System"));
+        assert!(!excluded("systemsettings.exe"));
+        assert!(!excluded("mysystemtool.exe"));
+        assert!(excluded("svchost.exe"));
+        assert!(!excluded("codec.exe"));
+        assert_eq!(rules.evaluate(&view("svchost.exe")).pattern(), Some("re:^svc"));
+        // Only "system" changed meaning since exclusions were substrings
+        assert_eq!(plain_entries(&rules.exclusions), vec!["system"]);
+
+        // The old behaviour stays available for entries without a prefix
+        config.whitelist.substring_exclusions = true;
+        let rules = RuleSet::from_config(&config).unwrap();
+        let decision = rules.evaluate(&view("SystemSettings.exe"));
+        assert_eq!(decision.outcome, MatchOutcome::Excluded { pattern: "system".to_string() });
+        assert!(rules.warned.lock().unwrap().contains(&("system".to_string(), "systemsettings.exe".to_string())));
+        rules.evaluate(&view("System"));
+        assert_eq!(rules.warned.lock().unwrap().len(), 1);
+        assert!(excluded("svchost.exe"));
+        assert!(!excluded("mysvc.exe"));
+
+        // In regex mode an entry without a prefix must match the whole name
+        config.whitelist.substring_exclusions = false;
+        config.whitelist.match_mode = MatchMode::Regex;
+        config.whitelist.processes = vec![r"\.exe$".to_string(), "^System$".to_string()];
+        config.whitelist.exclude_processes = vec!["System".to_string(), "re:Settings".to_string()];
+        let rules = RuleSet::from_config(&config).unwrap();
+        let excluded = |name: &str| matches!(rules.evaluate(&view(name)).outcome, MatchOutcome::Excluded { .. });
+        assert!(excluded("System"));
+        assert!(!excluded("SystemTray.exe"));
+        assert!(excluded("SystemSettings.exe"));
+    }
+
+    #[test]
+    fn test_per_entry_match_modes() {
+        let mut config = create_test_config();
+        config.whitelist.match_mode = MatchMode::Regex;
+        config.whitelist.processes = vec![
+            "re:^steam_.*".to_string(),
+            "exact:code.exe".to_string(),
+            "glob:*.game.exe".to_string(),
+            "^obs\\d*\\.exe$".to_string(),
+        ];
+        let config = with_rules(
+            config,
+            r#"
+            [[rules]]
+            name = "jvm"
+            processes = ["java*.exe"]
+            cmdline = ["re:-Xmx\\d+g", "*GradleDaemon*"]
+            target = "e_cores"
+            "#,
+        );
+        let rules = RuleSet::from_config(&config).unwrap();
+        let matched = |name: &str| rules.evaluate(&view(name)).pattern().map(str::to_string);
+
+        assert_eq!(matched("steam_api.exe").as_deref(), Some("re:^steam_.*"));
+        assert_eq!(matched("Code.exe").as_deref(), Some("exact:code.exe"));
+        // The dot in an exact or glob entry is literal
+        assert_eq!(matched("codeXexe"), None);
+        assert_eq!(matched("my.game.exe").as_deref(), Some("glob:*.game.exe"));
+        assert_eq!(matched("obs64.exe").as_deref(), Some("^obs\\d*\\.exe$"));
+
+        let daemon = view("javaw.exe").with_cmdline("javaw org.gradle.launcher.daemon.bootstrap.GradleDaemon");
+        let decision = rules.explain(&daemon);
+        assert_eq!(decision.rule_index(), Some(0));
+        assert_eq!(decision.tried[0].pattern, "re:-Xmx\\d+g");
+    }
+
+    #[test]
+    fn test_schedules() {
+        let config = with_rules(
+            create_test_config(),
+            r#"
+            [[rules]]
+            name = "builds"
+            processes = ["test.exe", "cl.exe"]
+            target = "e_cores"
+
+            [[rules.schedule]]
+            days = ["mon-fri"]
+            start = "09:00"
+            end = "18:00"
+            timezone = "utc"
+            "#,
+        );
+        let mut rules = RuleSet::from_config(&config).unwrap();
+        // Monday 2026-03-02
+        let at = |hour| Utc.with_ymd_and_hms(2026, 3, 2, hour, 0, 0).unwrap();
+
+        // Off until the first update
+        assert!(!rules.is_active(0));
+        assert_eq!(rules.update_schedules(at(10)), vec![0]);
+        assert!(rules.update_schedules(at(11)).is_empty());
+        assert_eq!(
+            rules.explain(&view("test.exe")).to_string(),
+            "test.exe: rule #0 \"builds\" matched pattern \"test.exe\" within schedule \"mon,tue,wed,thu,fri 09:00-18:00 utc\" -> e_cores"
+        );
+
+        assert_eq!(rules.update_schedules(at(18)), vec![0]);
+        assert_eq!(
+            rules.explain(&view("test.exe")).to_string(),
+            "test.exe: rule #1 \"whitelist\" matched pattern \"test.exe\" -> p_cores\n  \
+             tried rule #0 \"builds\" pattern \"test.exe\": matched, but outside the schedule \"mon,tue,wed,thu,fri 09:00-18:00 utc\""
+        );
+
+        // Without a later rule, the schedule is what kept the process unpinned
+        let decision = rules.evaluate(&view("cl.exe"));
+        assert_eq!(decision.rule_index(), None);
+        assert_eq!(
+            decision.to_string(),
+            "cl.exe: only rule #0 \"builds\" matched, but it is outside its schedule \"mon,tue,wed,thu,fri 09:00-18:00 utc\""
+        );
+        assert!(matches!(decision.outcome, MatchOutcome::OutsideSchedule { rule_index: 0, .. }));
+
+        let config = with_rules(
+            create_test_config(),
+            r#"
+            [[rules]]
+            name = "broken"
+            processes = ["test.exe"]
+            target = "e_cores"
+            schedule = [{ start = "25:00" }]
+            "#,
+        );
+        let error = RuleSet::from_config(&config).err().unwrap().to_string();
+        assert!(error.starts_with("Configuration error: Rule broken: invalid schedule start \"25:00\""), "{}", error);
+    }
+
+    #[test]
+    fn test_power_targets() {
+        let mut config = create_test_config();
+        config.whitelist.battery_target = Some(CoreTarget::ECores);
+        let config = with_rules(
+            config,
+            r#"
+            [[rules]]
+            name = "encoders"
+            processes = ["ffmpeg.exe"]
+            target = "e_cores"
+            ac_target = "all_cores"
+            "#,
+        );
+        let mut rules = RuleSet::from_config(&config).unwrap();
+        let target = |rules: &RuleSet, name: &str| match rules.evaluate(&view(name)).outcome {
+            MatchOutcome::Matched { target, .. } => target,
+            outcome => panic!("{:?}", outcome),
+        };
+
+        assert_eq!(rules.power_state(), PowerState::Ac);
+        assert_eq!(target(&rules, "test.exe"), CoreTarget::PCores);
+        assert_eq!(target(&rules, "ffmpeg.exe"), CoreTarget::AllCores);
+
+        assert!(rules.set_power_state(PowerState::Battery));
+        assert!(!rules.set_power_state(PowerState::Battery));
+        assert_eq!(target(&rules, "test.exe"), CoreTarget::ECores);
+        assert_eq!(target(&rules, "ffmpeg.exe"), CoreTarget::ECores);
+        assert!(rules.rules()[0].has_power_targets());
+        assert!(rules.has_power_targets());
+        assert!(!RuleSet::from_config(&create_test_config()).unwrap().has_power_targets());
+    }
+
+    #[test]
+    fn test_idle_demotion_config() {
+        let config = with_rules(
+            create_test_config(),
+            r#"
+            [[rules]]
+            name = "browsers"
+            processes = ["chrome.exe"]
+            [rules.idle_demotion]
+            promote_above_percent = 8.0
+            "#,
+        );
+        let rules = RuleSet::from_config(&config).unwrap();
+        let idle = rules.rules()[0].idle_demotion.as_ref().unwrap();
+        assert_eq!((idle.demote_below, idle.promote_above), (1.0, 8.0));
+        assert_eq!(idle.window.num_seconds(), 30);
+        assert_eq!(idle.target, CoreTarget::ECores);
+        assert!(rules.rules()[1].idle_demotion.is_none());
+
+        let config = with_rules(
+            create_test_config(),
+            r#"
+            [[rules]]
+            name = "browsers"
+            processes = ["chrome.exe"]
+            [rules.idle_demotion]
+            demote_below_percent = 5.0
+            promote_above_percent = 2.0
+            "#,
+        );
+        let error = RuleSet::from_config(&config).err().unwrap().to_string();
+        assert!(error.starts_with("Configuration error: Rule browsers: invalid idle_demotion"), "{}", error);
+    }
+
+    #[test]
+    fn test_invalid_regex_fails_at_load() {
+        let config = with_rules(
+            create_test_config(),
+            r#"
+            [[rules]]
+            name = "broken"
+            match_mode = "regex"
+            processes = ["^ok$", "(unclosed"]
+            target = "e_cores"
+            "#,
+        );
+        let error = RuleSet::from_config(&config).err().unwrap().to_string();
+        assert!(error.starts_with("Configuration error: Rule broken: invalid regex \"(unclosed\""), "{}", error);
+
+        let mut config = create_test_config();
+        config.whitelist.exclude_processes = vec!["re:[".to_string()];
+        let error = RuleSet::from_config(&config).err().unwrap().to_string();
+        assert!(error.starts_with("Configuration error: exclude_processes: invalid regex \"[\""), "{}", error);
+    }
+
+    #[test]
+    fn test_explain_output() {
+        let rules = RuleSet::from_config(&create_test_config()).unwrap();
+
+        let decision = rules.explain(&view("my.game.exe"));
+        assert_eq!(
+            decision.to_string(),
+            "my.game.exe: rule #0 \"whitelist\" matched pattern \"*.game.exe\" -> p_cores\n  \
+             tried rule #0 \"whitelist\" pattern \"test.exe\": no match"
+        );
+
+        let decision = rules.explain(&view("svchost.exe"));
+        assert_eq!(decision.outcome, MatchOutcome::Excluded { pattern: "exact:svchost.exe".to_string() });
+        assert_eq!(rules.explain(&view("notepad.exe")).outcome, MatchOutcome::NoMatch);
+    }
+}
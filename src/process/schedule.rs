@@ -0,0 +1,270 @@
+use crate::config::settings::ScheduleConfig;
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDateTime, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use std::fmt;
+use thiserror::Error;
+
+/// Error for a schedule window that could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid schedule {field} {value:?}: {reason}")]
+pub struct ScheduleError {
+    pub field: &'static str,
+    pub value: String,
+    pub reason: String,
+}
+
+/// Time zone a schedule window is read in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleZone {
+    /// The machine's local time, following its daylight saving changes
+    Local,
+    Fixed(FixedOffset),
+    /// An IANA zone such as `Europe/Berlin`, following its daylight saving
+    /// changes
+    Named(Tz),
+}
+
+impl ScheduleZone {
+    fn parse(value: &str) -> Result<Self, ScheduleError> {
+        match value.to_lowercase().as_str() {
+            "" | "local" => Ok(ScheduleZone::Local),
+            "utc" | "z" => Ok(ScheduleZone::Fixed(FixedOffset::east_opt(0).unwrap())),
+            _ => value
+                .parse::<FixedOffset>()
+                .map(ScheduleZone::Fixed)
+                .or_else(|_| value.parse::<Tz>().map(ScheduleZone::Named))
+                .map_err(|_| ScheduleError {
+                    field: "timezone",
+                    value: value.to_string(),
+                    reason: "expected local, utc, an offset such as +02:00 or a zone such as Europe/Berlin".to_string(),
+                }),
+        }
+    }
+
+    fn local_time(&self, now: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            ScheduleZone::Local => now.with_timezone(&Local).naive_local(),
+            ScheduleZone::Fixed(offset) => now.with_timezone(offset).naive_local(),
+            ScheduleZone::Named(zone) => now.with_timezone(zone).naive_local(),
+        }
+    }
+}
+
+/// One `[[rules.schedule]]` window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleWindow {
+    /// Days the window starts on; every day if empty
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    /// Not after `start` for windows that run past midnight
+    pub end: NaiveTime,
+    pub zone: ScheduleZone,
+}
+
+impl ScheduleWindow {
+    pub fn from_config(config: &ScheduleConfig) -> Result<Self, ScheduleError> {
+        let mut days = Vec::new();
+        for entry in &config.days {
+            for day in parse_days(entry)? {
+                if !days.contains(&day) {
+                    days.push(day);
+                }
+            }
+        }
+
+        Ok(Self {
+            days,
+            start: parse_time("start", &config.start)?,
+            end: parse_time("end", &config.end)?,
+            zone: ScheduleZone::parse(&config.timezone)?,
+        })
+    }
+
+    /// Whether `now` falls inside the window
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        let local = self.zone.local_time(now);
+        let (day, time) = (local.weekday(), local.time());
+        let starts_on = |day: Weekday| self.days.is_empty() || self.days.contains(&day);
+
+        if self.start < self.end {
+            starts_on(day) && self.start <= time && time < self.end
+        } else if self.start == self.end {
+            starts_on(day)
+        } else {
+            // Runs past midnight; the early hours belong to the previous day
+            (starts_on(day) && time >= self.start) || (starts_on(day.pred()) && time < self.end)
+        }
+    }
+}
+
+impl fmt::Display for ScheduleWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.days.is_empty() {
+            write!(f, "daily")?;
+        } else {
+            let days: Vec<String> = self.days.iter().map(|day| day.to_string().to_lowercase()).collect();
+            write!(f, "{}", days.join(","))?;
+        }
+        write!(f, " {}-{}", self.start.format("%H:%M"), self.end.format("%H:%M"))?;
+        match self.zone {
+            ScheduleZone::Local => Ok(()),
+            ScheduleZone::Fixed(offset) if offset.local_minus_utc() == 0 => write!(f, " utc"),
+            ScheduleZone::Fixed(offset) => write!(f, " {}", offset),
+            ScheduleZone::Named(zone) => write!(f, " {}", zone.name()),
+        }
+    }
+}
+
+/// The windows a rule applies in; it applies while any of them is open
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    pub windows: Vec<ScheduleWindow>,
+}
+
+impl Schedule {
+    /// `None` for an empty list, meaning the rule always applies
+    pub fn from_config(windows: &[ScheduleConfig]) -> Result<Option<Self>, ScheduleError> {
+        if windows.is_empty() {
+            return Ok(None);
+        }
+
+        let windows = windows.iter().map(ScheduleWindow::from_config).collect::<Result<_, _>>()?;
+        Ok(Some(Self { windows }))
+    }
+
+    pub fn is_open(&self, now: DateTime<Utc>) -> bool {
+        self.windows.iter().any(|window| window.contains(now))
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let windows: Vec<String> = self.windows.iter().map(ToString::to_string).collect();
+        write!(f, "{}", windows.join(" or "))
+    }
+}
+
+fn parse_time(field: &'static str, value: &str) -> Result<NaiveTime, ScheduleError> {
+    // 24:00 ends a window at midnight, which NaiveTime spells 00:00
+    if value == "24:00" {
+        return Ok(NaiveTime::MIN);
+    }
+
+    NaiveTime::parse_from_str(value, "%H:%M").map_err(|e| ScheduleError {
+        field,
+        value: value.to_string(),
+        reason: e.to_string(),
+    })
+}
+
+/// A weekday or an inclusive range such as `mon-fri` or `fri-mon`
+fn parse_days(entry: &str) -> Result<Vec<Weekday>, ScheduleError> {
+    let parse = |day: &str| {
+        day.trim().parse::<Weekday>().map_err(|_| ScheduleError {
+            field: "days",
+            value: entry.to_string(),
+            reason: "expected a weekday such as mon or a range such as mon-fri".to_string(),
+        })
+    };
+
+    match entry.split_once('-') {
+        Some((first, last)) => {
+            let (mut day, last) = (parse(first)?, parse(last)?);
+            let mut days = vec![day];
+            while day != last {
+                day = day.succ();
+                days.push(day);
+            }
+            Ok(days)
+        }
+        None => Ok(vec![parse(entry)?]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn window(days: &[&str], start: &str, end: &str, timezone: &str) -> ScheduleWindow {
+        ScheduleWindow::from_config(&ScheduleConfig {
+            days: days.iter().map(|day| day.to_string()).collect(),
+            start: start.to_string(),
+            end: end.to_string(),
+            timezone: timezone.to_string(),
+        })
+        .unwrap()
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        // 2026-03-02 is a Monday
+        Utc.with_ymd_and_hms(2026, 3, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_working_hours() {
+        let hours = window(&["mon-fri"], "09:00", "18:00", "utc");
+        assert_eq!(hours.days.len(), 5);
+        assert!(hours.contains(at(2, 9, 0)));
+        assert!(hours.contains(at(6, 17, 59)));
+        assert!(!hours.contains(at(6, 18, 0)));
+        assert!(!hours.contains(at(7, 12, 0)));
+        assert_eq!(hours.to_string(), "mon,tue,wed,thu,fri 09:00-18:00 utc");
+
+        // 09:00 at +02:00 is 07:00 UTC
+        let hours = window(&["Monday"], "09:00", "18:00", "+02:00");
+        assert!(hours.contains(at(2, 7, 0)));
+        assert!(!hours.contains(at(2, 6, 59)));
+    }
+
+    #[test]
+    fn test_named_zones() {
+        let hours = window(&["mon-fri"], "09:00", "18:00", "Europe/Berlin");
+        assert_eq!(hours.to_string(), "mon,tue,wed,thu,fri 09:00-18:00 Europe/Berlin");
+
+        // 09:00 in Berlin is 08:00 UTC in winter
+        assert!(hours.contains(at(2, 8, 0)));
+        assert!(!hours.contains(at(2, 7, 59)));
+
+        // and 07:00 UTC after the switch to summer time on 2026-03-29
+        let summer = Utc.with_ymd_and_hms(2026, 3, 30, 7, 0, 0).unwrap();
+        assert!(hours.contains(summer));
+        assert!(!hours.contains(summer - chrono::Duration::minutes(1)));
+    }
+
+    #[test]
+    fn test_windows_past_midnight() {
+        let evening = window(&["fri", "sat"], "19:00", "02:00", "utc");
+        assert!(evening.contains(at(6, 23, 0)));
+        // Saturday 01:00 still belongs to Friday's window
+        assert!(evening.contains(at(7, 1, 0)));
+        assert!(!evening.contains(at(7, 2, 0)));
+        // Friday 01:00 would belong to Thursday's
+        assert!(!evening.contains(at(6, 1, 0)));
+        assert!(evening.contains(at(8, 1, 59)));
+
+        let weekend = window(&["sun-mon"], "00:00", "24:00", "utc");
+        assert!(weekend.contains(at(8, 0, 0)));
+        assert!(weekend.contains(at(2, 23, 59)));
+        assert!(!weekend.contains(at(3, 0, 0)));
+        assert!(window(&[], "00:00", "00:00", "utc").contains(at(4, 12, 0)));
+    }
+
+    #[test]
+    fn test_errors() {
+        let config = |days: &str, start: &str, timezone: &str| ScheduleConfig {
+            days: vec![days.to_string()],
+            start: start.to_string(),
+            end: "18:00".to_string(),
+            timezone: timezone.to_string(),
+        };
+
+        let error = ScheduleWindow::from_config(&config("mon-fry", "09:00", "utc")).unwrap_err();
+        assert_eq!(error.field, "days");
+        assert_eq!(ScheduleWindow::from_config(&config("mon", "9am", "utc")).unwrap_err().field, "start");
+        let error = ScheduleWindow::from_config(&config("mon", "09:00", "Europe/Atlantis")).unwrap_err();
+        assert_eq!(error.field, "timezone");
+        assert!(error.reason.contains("Europe/Berlin"));
+        assert!(Schedule::from_config(&[]).unwrap().is_none());
+    }
+}
@@ -0,0 +1,104 @@
+use crate::utils::ServiceError;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    pub parent_pid: u32,
+}
+
+/// The account a process runs as
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessOwner {
+    /// `DOMAIN\user` on Windows, the login name on Linux; `None` if the
+    /// account cannot be resolved to a name
+    pub name: Option<String>,
+    /// Effective UID on Linux
+    pub uid: Option<u32>,
+}
+
+impl ProcessOwner {
+    /// The user name without its `DOMAIN\` prefix
+    pub fn user_name(&self) -> Option<&str> {
+        let name = self.name.as_deref()?;
+        Some(name.rsplit('\\').next().unwrap_or(name))
+    }
+}
+
+/// A backend that can enumerate the processes running on the system
+pub trait ProcessSource: Send {
+    /// Take a snapshot of all running processes
+    fn snapshot(&mut self) -> Result<Vec<ProcessInfo>, ServiceError>;
+
+    /// Full path of a process's executable
+    ///
+    /// Called only when a rule needs it, since it costs a system call per
+    /// process. `None` if the process has exited, access is denied, or the
+    /// source cannot tell.
+    fn exe_path(&self, _pid: u32) -> Option<PathBuf> {
+        None
+    }
+
+    /// Full command line of a process, arguments separated by spaces
+    ///
+    /// Like `exe_path`, only called when a rule needs it.
+    fn cmdline(&self, _pid: u32) -> Option<String> {
+        None
+    }
+
+    /// The account a process runs as; only called when a rule needs it
+    fn owner(&self, _pid: u32) -> Option<ProcessOwner> {
+        None
+    }
+
+    /// Login session of a process: the Terminal Services session on
+    /// Windows, the audit session on Linux; only called when a rule needs it
+    fn session(&self, _pid: u32) -> Option<u32> {
+        None
+    }
+
+    /// Total CPU time, user plus kernel, a process has used since it
+    /// started; only called for rules that demote idle processes
+    fn cpu_time(&self, _pid: u32) -> Option<Duration> {
+        None
+    }
+
+    /// When a process started, as the time since an epoch of the source's
+    /// choosing (boot on Linux, 1601 on Windows), so only comparable within
+    /// one source; used to tell a parent from a process that reused its PID
+    fn start_time(&self, _pid: u32) -> Option<Duration> {
+        None
+    }
+}
+
+/// Get the process source for the current platform
+pub fn default_source() -> Box<dyn ProcessSource> {
+    #[cfg(windows)]
+    {
+        Box::new(crate::process::ProcessMonitor)
+    }
+
+    #[cfg(not(windows))]
+    {
+        Box::new(crate::process::ProcFsSource::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_name() {
+        let owner = |name: &str| ProcessOwner {
+            name: Some(name.to_string()),
+            uid: None,
+        };
+        assert_eq!(owner("KIOSK-PC\\kiosk").user_name(), Some("kiosk"));
+        assert_eq!(owner("kiosk").user_name(), Some("kiosk"));
+        assert_eq!(ProcessOwner { name: None, uid: Some(1001) }.user_name(), None);
+    }
+}
@@ -0,0 +1,506 @@
+use crate::config::Config;
+use crate::cpu::CoreInfo;
+use crate::focus::FocusProvider;
+use crate::power::{PowerSource, PowerState};
+use crate::process::source::{ProcessInfo, ProcessOwner, ProcessSource};
+use crate::utils::{Clock, ServiceError};
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// First line of a trace: what a replay needs besides the snapshots to
+/// decide the way the recording machine did
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TraceHeader {
+    pub config: Option<Config>,
+    pub topology: Option<CoreInfo>,
+}
+
+/// Attributes of one process that the engine queried while deciding on a frame
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TracedAttributes {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exe_path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cmdline: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<ProcessOwner>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_time: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<Duration>,
+}
+
+/// One process-table snapshot in a JSON Lines trace file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceFrame {
+    pub timestamp: DateTime<FixedOffset>,
+    pub processes: Vec<ProcessInfo>,
+    /// Power state read for this frame; only there if some rule has AC or
+    /// battery targets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub power: Option<PowerState>,
+    /// PID owning the foreground window when this frame was decided
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreground: Option<u32>,
+    /// Queried attributes by PID; traces from older versions have none
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<u32, TracedAttributes>,
+}
+
+/// The first line of a trace file; every other line is a `TraceFrame`
+#[derive(Serialize, Deserialize)]
+struct HeaderLine {
+    header: TraceHeader,
+}
+
+/// Process source that records every snapshot of another source to a trace file
+///
+/// A frame is written when the next snapshot is taken, or when the recorder
+/// is dropped, so that it carries every attribute queried for it.
+pub struct RecordingSource {
+    inner: Box<dyn ProcessSource>,
+    writer: BufWriter<File>,
+    /// The latest snapshot, not written yet
+    pending: Option<TraceFrame>,
+    attributes: RefCell<BTreeMap<u32, TracedAttributes>>,
+    /// Power state read for the pending frame, shared with `RecordingPowerSource`
+    power: Arc<Mutex<Option<PowerState>>>,
+    /// Foreground PID read for the pending frame, shared with `RecordingFocusProvider`
+    foreground: Arc<Mutex<Option<u32>>>,
+}
+
+impl RecordingSource {
+    /// Wrap `inner`, writing `header` and then its snapshots to a new trace file at `path`
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        header: TraceHeader,
+        inner: Box<dyn ProcessSource>,
+    ) -> Result<Self, ServiceError> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = File::create(path).map_err(|e| {
+            ServiceError::ProcessMonitoring(format!("Failed to create trace file {:?}: {}", path, e))
+        })?;
+
+        let mut recorder = Self {
+            inner,
+            writer: BufWriter::new(file),
+            pending: None,
+            attributes: RefCell::new(BTreeMap::new()),
+            power: Arc::new(Mutex::new(None)),
+            foreground: Arc::new(Mutex::new(None)),
+        };
+        recorder.write_line(&HeaderLine { header })?;
+
+        log::info!("Recording process snapshots to {:?}", path);
+        Ok(recorder)
+    }
+
+    fn write_line<T: Serialize>(&mut self, line: &T) -> Result<(), ServiceError> {
+        serde_json::to_writer(&mut self.writer, line)
+            .map_err(|e| ServiceError::ProcessMonitoring(format!("Failed to serialize trace line: {}", e)))?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the pending frame with the attributes queried for it
+    fn write_pending(&mut self) {
+        let Some(mut frame) = self.pending.take() else {
+            return;
+        };
+        frame.attributes = self.attributes.take();
+        frame.power = self.power.lock().unwrap().take();
+        frame.foreground = self.foreground.lock().unwrap().take();
+
+        // A broken trace file should not stop the service from pinning processes
+        if let Err(e) = self.write_line(&frame) {
+            log::warn!("Failed to record process snapshot: {}", e);
+        }
+    }
+
+    /// Wrap `inner` so that the power states it reports go into the trace
+    /// with the frame they were read for
+    pub fn power_source(&self, inner: Box<dyn PowerSource>) -> RecordingPowerSource {
+        RecordingPowerSource {
+            inner,
+            power: Arc::clone(&self.power),
+        }
+    }
+
+    /// Wrap `inner` so that the foreground PIDs it reports go into the trace
+    /// with the frame they were read for
+    pub fn focus_provider(&self, inner: Box<dyn FocusProvider>) -> RecordingFocusProvider {
+        RecordingFocusProvider {
+            inner,
+            foreground: Arc::clone(&self.foreground),
+        }
+    }
+
+    fn remember<T>(&self, pid: u32, value: Option<T>, field: impl FnOnce(&mut TracedAttributes, T)) -> Option<T>
+    where
+        T: Clone,
+    {
+        if let Some(value) = &value {
+            field(self.attributes.borrow_mut().entry(pid).or_default(), value.clone());
+        }
+        value
+    }
+}
+
+impl ProcessSource for RecordingSource {
+    fn snapshot(&mut self) -> Result<Vec<ProcessInfo>, ServiceError> {
+        self.write_pending();
+        let processes = self.inner.snapshot()?;
+
+        self.pending = Some(TraceFrame {
+            timestamp: Local::now().fixed_offset(),
+            processes: processes.clone(),
+            power: None,
+            foreground: None,
+            attributes: BTreeMap::new(),
+        });
+        Ok(processes)
+    }
+
+    fn exe_path(&self, pid: u32) -> Option<PathBuf> {
+        self.remember(pid, self.inner.exe_path(pid), |a, v| a.exe_path = Some(v))
+    }
+
+    fn cmdline(&self, pid: u32) -> Option<String> {
+        self.remember(pid, self.inner.cmdline(pid), |a, v| a.cmdline = Some(v))
+    }
+
+    fn owner(&self, pid: u32) -> Option<ProcessOwner> {
+        self.remember(pid, self.inner.owner(pid), |a, v| a.owner = Some(v))
+    }
+
+    fn session(&self, pid: u32) -> Option<u32> {
+        self.remember(pid, self.inner.session(pid), |a, v| a.session = Some(v))
+    }
+
+    fn cpu_time(&self, pid: u32) -> Option<Duration> {
+        self.remember(pid, self.inner.cpu_time(pid), |a, v| a.cpu_time = Some(v))
+    }
+
+    fn start_time(&self, pid: u32) -> Option<Duration> {
+        self.remember(pid, self.inner.start_time(pid), |a, v| a.start_time = Some(v))
+    }
+}
+
+impl Drop for RecordingSource {
+    fn drop(&mut self) {
+        self.write_pending();
+    }
+}
+
+/// Power source handed out by `RecordingSource::power_source`
+pub struct RecordingPowerSource {
+    inner: Box<dyn PowerSource>,
+    power: Arc<Mutex<Option<PowerState>>>,
+}
+
+impl PowerSource for RecordingPowerSource {
+    fn power_state(&self) -> Option<PowerState> {
+        let state = self.inner.power_state();
+        *self.power.lock().unwrap() = state;
+        state
+    }
+}
+
+/// Focus provider handed out by `RecordingSource::focus_provider`
+pub struct RecordingFocusProvider {
+    inner: Box<dyn FocusProvider>,
+    foreground: Arc<Mutex<Option<u32>>>,
+}
+
+impl FocusProvider for RecordingFocusProvider {
+    fn foreground_pid(&self) -> Option<u32> {
+        let pid = self.inner.foreground_pid();
+        *self.foreground.lock().unwrap() = pid;
+        pid
+    }
+
+    fn unavailable(&self) -> Option<String> {
+        self.inner.unavailable()
+    }
+}
+
+/// Clock handed out by `ReplaySource::clock`, set to each frame's timestamp
+/// as the frame is replayed
+#[derive(Debug, Clone)]
+pub struct ReplayClock(Arc<Mutex<DateTime<Utc>>>);
+
+impl Clock for ReplayClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// Power source handed out by `ReplaySource::power_source`, reporting the
+/// power state recorded with the frame being replayed
+#[derive(Debug, Clone)]
+pub struct ReplayPowerSource(Arc<Mutex<Option<PowerState>>>);
+
+impl PowerSource for ReplayPowerSource {
+    fn power_state(&self) -> Option<PowerState> {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// Focus provider handed out by `ReplaySource::focus_provider`, reporting
+/// the foreground PID recorded with the frame being replayed
+#[derive(Debug, Clone)]
+pub struct ReplayFocusProvider(Arc<Mutex<Option<u32>>>);
+
+impl FocusProvider for ReplayFocusProvider {
+    fn foreground_pid(&self) -> Option<u32> {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// Process source that plays back a recorded trace, one frame per snapshot
+///
+/// Attributes come from the frame being replayed, `clock` reads that
+/// frame's timestamp, `power_source` its power state and `focus_provider`
+/// its foreground PID.
+pub struct ReplaySource {
+    header: Option<TraceHeader>,
+    frames: VecDeque<TraceFrame>,
+    attributes: BTreeMap<u32, TracedAttributes>,
+    clock: ReplayClock,
+    power: ReplayPowerSource,
+    focus: ReplayFocusProvider,
+}
+
+impl ReplaySource {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ServiceError> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| {
+            ServiceError::ProcessMonitoring(format!("Failed to open trace file {:?}: {}", path, e))
+        })?;
+
+        Self::from_reader(BufReader::new(file))
+    }
+
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, ServiceError> {
+        let mut header = None;
+        let mut frames: VecDeque<TraceFrame> = VecDeque::new();
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let invalid = |e: serde_json::Error| {
+                ServiceError::ProcessMonitoring(format!("Invalid trace frame on line {}: {}", index + 1, e))
+            };
+            let value: serde_json::Value = serde_json::from_str(&line).map_err(invalid)?;
+
+            if value.get("header").is_none() {
+                frames.push_back(serde_json::from_value(value).map_err(invalid)?);
+            } else if header.is_none() && frames.is_empty() {
+                header = Some(serde_json::from_value::<HeaderLine>(value).map_err(invalid)?.header);
+            } else {
+                return Err(ServiceError::ProcessMonitoring(format!(
+                    "Unexpected trace header on line {}",
+                    index + 1
+                )));
+            }
+        }
+
+        let start = frames.front().map_or_else(Utc::now, |frame| frame.timestamp.with_timezone(&Utc));
+        Ok(Self {
+            header,
+            frames,
+            attributes: BTreeMap::new(),
+            clock: ReplayClock(Arc::new(Mutex::new(start))),
+            power: ReplayPowerSource(Arc::new(Mutex::new(None))),
+            focus: ReplayFocusProvider(Arc::new(Mutex::new(None))),
+        })
+    }
+
+    /// Configuration and topology of the recording machine; `None` for
+    /// traces written before headers were recorded
+    pub fn header(&self) -> Option<&TraceHeader> {
+        self.header.as_ref()
+    }
+
+    /// A clock showing the timestamp of the frame being replayed
+    pub fn clock(&self) -> ReplayClock {
+        self.clock.clone()
+    }
+
+    /// A power source reporting the power state recorded with the frame
+    /// being replayed, or none if it was not read for that frame
+    pub fn power_source(&self) -> ReplayPowerSource {
+        self.power.clone()
+    }
+
+    /// A focus provider reporting the foreground PID recorded with the
+    /// frame being replayed
+    pub fn focus_provider(&self) -> ReplayFocusProvider {
+        self.focus.clone()
+    }
+
+    /// Number of frames left to replay
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    fn attribute<T>(&self, pid: u32, field: impl FnOnce(&TracedAttributes) -> Option<T>) -> Option<T> {
+        self.attributes.get(&pid).and_then(field)
+    }
+}
+
+impl ProcessSource for ReplaySource {
+    fn snapshot(&mut self) -> Result<Vec<ProcessInfo>, ServiceError> {
+        let frame = self
+            .frames
+            .pop_front()
+            .ok_or_else(|| ServiceError::ProcessMonitoring("Trace replay finished".to_string()))?;
+
+        log::info!(
+            "Replaying snapshot from {} ({} processes)",
+            frame.timestamp,
+            frame.processes.len()
+        );
+        *self.clock.0.lock().unwrap() = frame.timestamp.with_timezone(&Utc);
+        *self.power.0.lock().unwrap() = frame.power;
+        *self.focus.0.lock().unwrap() = frame.foreground;
+        self.attributes = frame.attributes;
+        Ok(frame.processes)
+    }
+
+    fn exe_path(&self, pid: u32) -> Option<PathBuf> {
+        self.attribute(pid, |a| a.exe_path.clone())
+    }
+
+    fn cmdline(&self, pid: u32) -> Option<String> {
+        self.attribute(pid, |a| a.cmdline.clone())
+    }
+
+    fn owner(&self, pid: u32) -> Option<ProcessOwner> {
+        self.attribute(pid, |a| a.owner.clone())
+    }
+
+    fn session(&self, pid: u32) -> Option<u32> {
+        self.attribute(pid, |a| a.session)
+    }
+
+    fn cpu_time(&self, pid: u32) -> Option<Duration> {
+        self.attribute(pid, |a| a.cpu_time)
+    }
+
+    fn start_time(&self, pid: u32) -> Option<Duration> {
+        self.attribute(pid, |a| a.start_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::SimulatedSystem;
+
+    #[test]
+    fn test_record_and_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.jsonl");
+
+        let sim = SimulatedSystem::new(8);
+        sim.spawn(100, "game.exe", 1);
+        sim.set_exe_path(100, "C:\\Games\\game.exe");
+
+        let header = TraceHeader {
+            config: Some(Config::default()),
+            topology: None,
+        };
+        let mut recorder = RecordingSource::create(&path, header, Box::new(sim.clone())).unwrap();
+        let power = recorder.power_source(Box::new(sim.clone()));
+        let focus = recorder.focus_provider(Box::new(sim.clone()));
+        recorder.snapshot().unwrap();
+        assert!(recorder.exe_path(100).is_some());
+        sim.spawn(101, "child.exe", 100);
+        sim.set_power_state(PowerState::Battery);
+        recorder.snapshot().unwrap();
+        assert_eq!(power.power_state(), Some(PowerState::Battery));
+        sim.set_foreground(Some(101));
+        assert_eq!(focus.foreground_pid(), Some(101));
+        drop(recorder);
+
+        let mut replay = ReplaySource::open(&path).unwrap();
+        assert_eq!(replay.len(), 2);
+        let config = replay.header().unwrap().config.as_ref().unwrap();
+        assert_eq!(config.service.scan_interval_ms, Config::default().service.scan_interval_ms);
+
+        let power = replay.power_source();
+        let focus = replay.focus_provider();
+        let first = replay.snapshot().unwrap();
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].name, "game.exe");
+        assert_eq!(replay.exe_path(100), Some(PathBuf::from("C:\\Games\\game.exe")));
+        assert_eq!(power.power_state(), None);
+        assert_eq!(focus.foreground_pid(), None);
+
+        // Only what was queried while recording is in the trace
+        let second = replay.snapshot().unwrap();
+        assert_eq!(second.len(), 2);
+        assert_eq!(second[1].parent_pid, 100);
+        assert_eq!(replay.exe_path(100), None);
+        assert_eq!(power.power_state(), Some(PowerState::Battery));
+        assert_eq!(focus.foreground_pid(), Some(101));
+
+        assert!(replay.is_empty());
+        assert!(replay.snapshot().is_err());
+    }
+
+    #[test]
+    fn test_replay_clock_follows_frames() {
+        let trace = concat!(
+            "{\"timestamp\":\"2026-01-20T10:00:00+01:00\",\"processes\":[]}\n",
+            "{\"timestamp\":\"2026-01-20T10:00:05+01:00\",\"processes\":[],",
+            "\"attributes\":{\"100\":{\"cmdline\":\"game.exe -dx12\",\"session\":1}}}\n",
+        );
+        let mut replay = ReplaySource::from_reader(trace.as_bytes()).unwrap();
+        let clock = replay.clock();
+        assert!(replay.header().is_none());
+
+        replay.snapshot().unwrap();
+        assert_eq!(clock.now().to_rfc3339(), "2026-01-20T09:00:00+00:00");
+        assert_eq!(replay.cmdline(100), None);
+
+        replay.snapshot().unwrap();
+        assert_eq!(clock.now().to_rfc3339(), "2026-01-20T09:00:05+00:00");
+        assert_eq!(replay.cmdline(100).as_deref(), Some("game.exe -dx12"));
+        assert_eq!(replay.session(100), Some(1));
+    }
+
+    #[test]
+    fn test_invalid_trace_line() {
+        let trace = "{\"timestamp\":\"2026-01-20T10:00:00+01:00\",\"processes\":[]}\nnot json\n";
+        let error = ReplaySource::from_reader(trace.as_bytes()).err().unwrap();
+        assert!(error.to_string().contains("line 2"));
+
+        let trace = "{\"timestamp\":\"2026-01-20T10:00:00+01:00\",\"processes\":[]}\n{\"header\":{}}\n";
+        let error = ReplaySource::from_reader(trace.as_bytes()).err().unwrap();
+        assert!(error.to_string().contains("header on line 2"));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::process::source::ProcessInfo;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// Parent/child links between the processes of one snapshot
+///
+/// Only links to processes present in the same snapshot are kept, so a
+/// process whose parent has exited is a root.
+pub struct ProcessTree<'a> {
+    processes: HashMap<u32, &'a ProcessInfo>,
+    /// Start times looked up so far, so each PID is queried once per snapshot
+    start_times: RefCell<HashMap<u32, Option<Duration>>>,
+}
+
+impl<'a> ProcessTree<'a> {
+    pub fn from_snapshot(processes: &'a [ProcessInfo]) -> Self {
+        Self {
+            processes: processes.iter().map(|p| (p.pid, p)).collect(),
+            start_times: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, pid: u32) -> Option<&'a ProcessInfo> {
+        self.processes.get(&pid).copied()
+    }
+
+    /// The parent of a process, if it is in the snapshot
+    pub fn parent(&self, pid: u32) -> Option<&'a ProcessInfo> {
+        let process = self.get(pid)?;
+        if process.parent_pid == pid {
+            return None;
+        }
+        self.get(process.parent_pid)
+    }
+
+    /// Ancestors of a process from its parent upwards, each with its depth
+    /// (1 for the parent)
+    ///
+    /// A parent that `start_time` says started after its child has reused
+    /// the PID of the real parent, which exited, so the walk stops there;
+    /// links with an unknown start time are kept. Start times are looked up
+    /// only as the walk goes on, and once per PID for the life of the tree.
+    /// It also stops at the first PID seen twice,
+    /// since reused PIDs can make the recorded parent links loop.
+    pub fn ancestors<'t, F>(&'t self, pid: u32, start_time: F) -> Ancestors<'t, 'a, F>
+    where
+        F: Fn(u32) -> Option<Duration>,
+    {
+        Ancestors {
+            tree: self,
+            start_time,
+            current: pid,
+            depth: 0,
+            seen: HashSet::from([pid]),
+        }
+    }
+
+    fn start_time(&self, pid: u32, lookup: impl Fn(u32) -> Option<Duration>) -> Option<Duration> {
+        *self.start_times.borrow_mut().entry(pid).or_insert_with(|| lookup(pid))
+    }
+}
+
+/// Iterator over the ancestors of a process; see `ProcessTree::ancestors`
+pub struct Ancestors<'t, 'a, F> {
+    tree: &'t ProcessTree<'a>,
+    start_time: F,
+    current: u32,
+    depth: u32,
+    seen: HashSet<u32>,
+}
+
+impl<'a, F> Iterator for Ancestors<'_, 'a, F>
+where
+    F: Fn(u32) -> Option<Duration>,
+{
+    type Item = (&'a ProcessInfo, u32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let parent = self.tree.parent(self.current)?;
+        if !self.seen.insert(parent.pid) {
+            return None;
+        }
+
+        let child_started = self.tree.start_time(self.current, &self.start_time);
+        let parent_started = self.tree.start_time(parent.pid, &self.start_time);
+        if let (Some(child_started), Some(parent_started)) = (child_started, parent_started) {
+            if parent_started > child_started {
+                return None;
+            }
+        }
+
+        self.current = parent.pid;
+        self.depth += 1;
+        Some((parent, self.depth))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, name: &str, parent_pid: u32) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            name: name.to_string(),
+            parent_pid,
+        }
+    }
+
+    #[test]
+    fn test_links() {
+        let processes = vec![
+            process(4, "explorer.exe", 1),
+            process(10, "devenv.exe", 4),
+            process(11, "msbuild.exe", 10),
+            process(12, "cl.exe", 11),
+            process(13, "link.exe", 11),
+        ];
+        let tree = ProcessTree::from_snapshot(&processes);
+
+        assert_eq!(tree.parent(12).map(|p| p.pid), Some(11));
+        // PID 1 is not in the snapshot
+        assert!(tree.parent(4).is_none());
+
+        let ancestors: Vec<(u32, u32)> = tree.ancestors(12, |_| None).map(|(p, depth)| (p.pid, depth)).collect();
+        assert_eq!(ancestors, vec![(11, 1), (10, 2), (4, 3)]);
+    }
+
+    #[test]
+    fn test_cycles_terminate() {
+        // A reused PID can make two processes each other's parent
+        let processes = vec![process(20, "a.exe", 21), process(21, "b.exe", 20), process(22, "c.exe", 22)];
+        let tree = ProcessTree::from_snapshot(&processes);
+
+        assert_eq!(tree.ancestors(20, |_| None).count(), 1);
+        assert_eq!(tree.ancestors(22, |_| None).count(), 0);
+    }
+
+    #[test]
+    fn test_reused_parent_pids_are_not_parents() {
+        // devenv.exe (10) exited and its PID went to a later updater.exe
+        let processes = vec![
+            process(4, "explorer.exe", 1),
+            process(10, "updater.exe", 4),
+            process(11, "msbuild.exe", 10),
+        ];
+        let tree = ProcessTree::from_snapshot(&processes);
+        let started = |pid: u32| Some(Duration::from_secs(match pid {
+            4 => 1,
+            11 => 20,
+            10 => 30,
+            _ => return None,
+        }));
+
+        assert_eq!(tree.ancestors(11, started).count(), 0);
+        assert_eq!(tree.ancestors(10, started).map(|(p, _)| p.pid).collect::<Vec<_>>(), vec![4]);
+        // Unknown start times keep the link
+        let tree = ProcessTree::from_snapshot(&processes);
+        assert_eq!(tree.ancestors(11, |pid| started(pid).filter(|_| pid != 10)).count(), 2);
+    }
+}
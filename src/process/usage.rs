@@ -0,0 +1,165 @@
+use crate::config::settings::IdleDemotionConfig;
+use crate::cpu::CoreTarget;
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Error for an `idle_demotion` table with unusable thresholds
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid idle_demotion: {0}")]
+pub struct IdleDemotionError(pub String);
+
+/// Thresholds for moving idle processes off a rule's target and back
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdleDemotion {
+    /// Percent of one logical processor
+    pub demote_below: f64,
+    pub promote_above: f64,
+    pub window: chrono::Duration,
+    pub target: CoreTarget,
+}
+
+impl IdleDemotion {
+    pub fn from_config(config: &IdleDemotionConfig) -> Result<Self, IdleDemotionError> {
+        let (demote_below, promote_above) = (config.demote_below_percent, config.promote_above_percent);
+        if !(demote_below >= 0.0 && demote_below.is_finite()) {
+            return Err(IdleDemotionError(format!(
+                "demote_below_percent must be a non-negative number, got {}",
+                demote_below
+            )));
+        }
+        if !(promote_above > demote_below && promote_above.is_finite()) {
+            return Err(IdleDemotionError(format!(
+                "promote_above_percent ({}) must be above demote_below_percent ({})",
+                promote_above, demote_below
+            )));
+        }
+        if config.window_secs == 0 {
+            return Err(IdleDemotionError("window_secs must be at least 1".to_string()));
+        }
+
+        Ok(Self {
+            demote_below,
+            promote_above,
+            window: chrono::Duration::seconds(config.window_secs.min(i64::MAX as u64 / 1000) as i64),
+            target: config.target.clone(),
+        })
+    }
+
+    /// Whether a process should be demoted after averaging `percent`,
+    /// given whether it is demoted now
+    ///
+    /// Between the two thresholds a process stays where it is.
+    pub fn should_demote(&self, demoted: bool, percent: f64) -> bool {
+        if demoted {
+            percent <= self.promote_above
+        } else {
+            percent < self.demote_below
+        }
+    }
+}
+
+impl fmt::Display for IdleDemotion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} below {}%, back above {}% over {}s",
+            self.target,
+            self.demote_below,
+            self.promote_above,
+            self.window.num_seconds()
+        )
+    }
+}
+
+/// Cumulative CPU time readings of one process, kept for one window
+#[derive(Debug, Clone, Default)]
+pub struct CpuSamples {
+    samples: VecDeque<(DateTime<Utc>, Duration)>,
+}
+
+impl CpuSamples {
+    /// Add a reading, dropping those no longer needed to cover `window`
+    pub fn record(&mut self, at: DateTime<Utc>, cpu_time: Duration, window: chrono::Duration) {
+        // A reading lower than the last means the PID now names another process
+        if self.samples.back().is_some_and(|&(_, last)| cpu_time < last) {
+            self.samples.clear();
+        }
+        self.samples.push_back((at, cpu_time));
+
+        // Keep the newest reading that is at least a window old
+        while self.samples.len() > 1 && at - self.samples[1].0 >= window {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Average CPU use in percent of one processor, once the readings span
+    /// at least `window`
+    pub fn average_percent(&self, window: chrono::Duration) -> Option<f64> {
+        let (&(start, start_cpu), &(end, end_cpu)) = (self.samples.front()?, self.samples.back()?);
+        let span = end - start;
+        if span < window || span <= chrono::Duration::zero() {
+            return None;
+        }
+
+        Some((end_cpu - start_cpu).as_secs_f64() / span.to_std().ok()?.as_secs_f64() * 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn demotion(demote_below: f64, promote_above: f64, window_secs: u64) -> Result<IdleDemotion, IdleDemotionError> {
+        IdleDemotion::from_config(&IdleDemotionConfig {
+            demote_below_percent: demote_below,
+            promote_above_percent: promote_above,
+            window_secs,
+            target: CoreTarget::ECores,
+        })
+    }
+
+    #[test]
+    fn test_hysteresis() {
+        let idle = demotion(2.0, 10.0, 30).unwrap();
+        assert!(idle.should_demote(false, 1.5));
+        assert!(!idle.should_demote(false, 5.0));
+        assert!(idle.should_demote(true, 5.0));
+        assert!(idle.should_demote(true, 10.0));
+        assert!(!idle.should_demote(true, 10.5));
+        assert_eq!(idle.to_string(), "e_cores below 2%, back above 10% over 30s");
+
+        assert!(demotion(5.0, 5.0, 30).unwrap_err().0.contains("promote_above_percent"));
+        assert!(demotion(-1.0, 5.0, 30).is_err());
+        assert!(demotion(1.0, 5.0, 0).is_err());
+    }
+
+    #[test]
+    fn test_average_over_window() {
+        let start = Utc.with_ymd_and_hms(2026, 3, 2, 12, 0, 0).unwrap();
+        let window = chrono::Duration::seconds(10);
+        let mut samples = CpuSamples::default();
+
+        // 50ms of CPU per 5s scan is 1%
+        for scan in 0..3 {
+            let at = start + chrono::Duration::seconds(scan * 5);
+            samples.record(at, Duration::from_millis(scan as u64 * 50), window);
+            if scan < 2 {
+                assert_eq!(samples.average_percent(window), None);
+            }
+        }
+        assert!((samples.average_percent(window).unwrap() - 1.0).abs() < 1e-9);
+
+        // A busy scan counts for the window that contains it
+        samples.record(start + chrono::Duration::seconds(15), Duration::from_millis(1100), window);
+        assert!((samples.average_percent(window).unwrap() - 10.5).abs() < 1e-9);
+        assert_eq!(samples.samples.len(), 3);
+
+        // PID reuse restarts the window
+        samples.record(start + chrono::Duration::seconds(20), Duration::from_millis(10), window);
+        assert_eq!(samples.average_percent(window), None);
+    }
+}
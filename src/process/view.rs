@@ -0,0 +1,144 @@
+use crate::process::source::{ProcessInfo, ProcessOwner, ProcessSource};
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::path::{Path, PathBuf};
+
+/// A process as seen by rule evaluation
+///
+/// Wraps the cheap snapshot fields and fetches the costly attributes from
+/// the source on first use, so a lookup only happens for processes that
+/// reach a rule that needs it. Each attribute is fetched at most once.
+pub struct ProcessView<'a> {
+    info: Cow<'a, ProcessInfo>,
+    source: Option<&'a dyn ProcessSource>,
+    exe_path: OnceCell<Option<PathBuf>>,
+    cmdline: OnceCell<Option<String>>,
+    owner: OnceCell<Option<ProcessOwner>>,
+    session: OnceCell<Option<u32>>,
+}
+
+impl<'a> ProcessView<'a> {
+    /// View a snapshot entry, fetching further attributes from `source`
+    pub fn new(info: &'a ProcessInfo, source: &'a dyn ProcessSource) -> Self {
+        Self {
+            info: Cow::Borrowed(info),
+            source: Some(source),
+            exe_path: OnceCell::new(),
+            cmdline: OnceCell::new(),
+            owner: OnceCell::new(),
+            session: OnceCell::new(),
+        }
+    }
+
+    /// View a process known only by name; every other attribute is unknown
+    /// unless set with the `with_*` methods
+    pub fn from_name(process_name: &str) -> ProcessView<'static> {
+        ProcessView {
+            info: Cow::Owned(ProcessInfo {
+                pid: 0,
+                name: process_name.to_string(),
+                parent_pid: 0,
+            }),
+            source: None,
+            exe_path: OnceCell::new(),
+            cmdline: OnceCell::new(),
+            owner: OnceCell::new(),
+            session: OnceCell::new(),
+        }
+    }
+
+    /// Use a known executable path instead of asking the source
+    pub fn with_exe_path<P: Into<PathBuf>>(self, path: P) -> Self {
+        let _ = self.exe_path.set(Some(path.into()));
+        self
+    }
+
+    /// Use a known command line instead of asking the source
+    pub fn with_cmdline(self, cmdline: &str) -> Self {
+        let _ = self.cmdline.set(Some(cmdline.to_string()));
+        self
+    }
+
+    /// Use a known owner instead of asking the source
+    pub fn with_owner(self, owner: ProcessOwner) -> Self {
+        let _ = self.owner.set(Some(owner));
+        self
+    }
+
+    /// Use a known session instead of asking the source
+    pub fn with_session(self, session: u32) -> Self {
+        let _ = self.session.set(Some(session));
+        self
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.info.pid
+    }
+
+    pub fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    /// Full path of the executable, looked up on first call
+    pub fn exe_path(&self) -> Option<&Path> {
+        self.exe_path
+            .get_or_init(|| self.source.and_then(|source| source.exe_path(self.info.pid)))
+            .as_deref()
+    }
+
+    /// Full command line, looked up on first call
+    pub fn cmdline(&self) -> Option<&str> {
+        self.cmdline
+            .get_or_init(|| self.source.and_then(|source| source.cmdline(self.info.pid)))
+            .as_deref()
+    }
+
+    /// The account the process runs as, looked up on first call
+    pub fn owner(&self) -> Option<&ProcessOwner> {
+        self.owner
+            .get_or_init(|| self.source.and_then(|source| source.owner(self.info.pid)))
+            .as_ref()
+    }
+
+    /// Login session, looked up on first call
+    pub fn session(&self) -> Option<u32> {
+        *self
+            .session
+            .get_or_init(|| self.source.and_then(|source| source.session(self.info.pid)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::SimulatedSystem;
+
+    #[test]
+    fn test_exe_path_is_fetched_once() {
+        let mut sim = SimulatedSystem::new(4);
+        sim.spawn(10, "game.exe", 1);
+        sim.set_exe_path(10, "D:/Games/game.exe");
+        let processes = sim.snapshot().unwrap();
+
+        let view = ProcessView::new(&processes[0], &sim);
+        assert_eq!(sim.exe_path_queries(), 0);
+        assert_eq!(view.exe_path(), Some(Path::new("D:/Games/game.exe")));
+        assert_eq!(view.exe_path(), Some(Path::new("D:/Games/game.exe")));
+        assert_eq!(sim.exe_path_queries(), 1);
+        assert_eq!(view.cmdline(), None);
+        assert_eq!(sim.cmdline_queries(), 1);
+    }
+
+    #[test]
+    fn test_from_name() {
+        let view = ProcessView::from_name("game.exe");
+        assert_eq!(view.name(), "game.exe");
+        assert_eq!(view.exe_path(), None);
+        assert_eq!(view.cmdline(), None);
+        assert_eq!(view.owner(), None);
+        assert_eq!(view.session(), None);
+
+        let view = ProcessView::from_name("game.exe").with_exe_path("D:/Games/game.exe");
+        assert_eq!(view.exe_path(), Some(Path::new("D:/Games/game.exe")));
+    }
+}
@@ -1,5 +1,15 @@
 pub mod runner;
+#[cfg(windows)]
 pub mod service_manager;
 
 pub use runner::ServiceRunner;
+#[cfg(windows)]
 pub use service_manager::{run_service, ServiceControl};
+
+/// Windows services are not available on other platforms
+#[cfg(not(windows))]
+pub fn run_service() -> Result<(), crate::utils::ServiceError> {
+    Err(crate::utils::ServiceError::Service(
+        "Service mode is only supported on Windows".to_string(),
+    ))
+}
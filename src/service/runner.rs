@@ -1,7 +1,14 @@
 use crate::config::{Config, ConfigLoader};
-use crate::cpu::{AffinityManager, CpuDetector, DetectionMode};
-use crate::process::ProcessManager;
-use crate::utils::ServiceError;
+use crate::cpu::{
+    default_backend, AffinityBackend, AffinityManager, CoreInfo, CpuDetector, DryRunBackend,
+};
+use crate::focus::{default_focus_provider, FocusProvider};
+use crate::power::{default_power_source, PowerSource};
+use crate::process::{
+    default_source, ProcessManager, ProcessSource, ProcessView, RecordingSource, ReplaySource, RuleSet,
+    TraceHeader,
+};
+use crate::utils::{Clock, ServiceError, SystemClock};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -9,43 +16,203 @@ use std::time::Duration;
 pub struct ServiceRunner {
     config: Arc<Config>,
     process_manager: ProcessManager,
+    /// Number of trace frames left when replaying a recorded trace
+    replay_frames: Option<usize>,
 }
 
 impl ServiceRunner {
     pub fn new(config_path: &str) -> Result<Self, ServiceError> {
-        // Load configuration
+        let config = Self::load(config_path)?;
+        let core_info = Self::detect(&config)?;
+        Self::build(
+            config,
+            core_info,
+            default_source(),
+            default_backend(),
+            Box::new(SystemClock),
+            default_power_source(),
+            default_focus_provider(),
+        )
+    }
+
+    /// Run normally, additionally writing every process snapshot to a trace file
+    ///
+    /// The trace starts with the configuration and the detected topology,
+    /// and each snapshot also records the power state and foreground PID
+    /// read for it.
+    pub fn recording(config_path: &str, trace_path: &str) -> Result<Self, ServiceError> {
+        let config = Self::load(config_path)?;
+        let core_info = Self::detect(&config)?;
+
+        let header = TraceHeader {
+            config: Some(config.clone()),
+            topology: Some(CoreInfo::clone(&core_info)),
+        };
+        let source = RecordingSource::create(trace_path, header, default_source())?;
+        let power = source.power_source(default_power_source());
+        let focus = source.focus_provider(default_focus_provider());
+        Self::build(
+            config,
+            core_info,
+            Box::new(source),
+            default_backend(),
+            Box::new(SystemClock),
+            Box::new(power),
+            Box::new(focus),
+        )
+    }
+
+    /// Feed a recorded trace through the engine without touching any process
+    ///
+    /// Uses the configuration recorded in the trace unless `config_path` is
+    /// given, and the recorded topology, so the replay decides as the
+    /// recording machine did. Time, the power state and the foreground
+    /// window follow the frames.
+    pub fn replay(config_path: Option<&str>, trace_path: &str) -> Result<Self, ServiceError> {
+        let source = ReplaySource::open(trace_path)?;
+        let frames = source.len();
+        let header = source.header().cloned().unwrap_or_default();
+
+        let config = match (config_path, header.config) {
+            (Some(config_path), _) => Self::load(config_path)?,
+            (None, Some(config)) => {
+                Self::start(&config, &format!("trace {}", trace_path));
+                config
+            }
+            (None, None) => Self::load("config.toml")?,
+        };
+
+        let core_info = match header.topology {
+            Some(core_info) => {
+                log::info!("CPU topology recorded in trace: {}", core_info);
+                Arc::new(core_info)
+            }
+            None => Self::detect(&config)?,
+        };
+
+        let clock = source.clock();
+        let power = source.power_source();
+        let focus = source.focus_provider();
+        let mut runner = Self::build(
+            config,
+            core_info,
+            Box::new(source),
+            Box::new(DryRunBackend::new()),
+            Box::new(clock),
+            Box::new(power),
+            Box::new(focus),
+        )?;
+        log::info!("Replaying {} snapshots from {}", frames, trace_path);
+        runner.replay_frames = Some(frames);
+        Ok(runner)
+    }
+
+    /// Describe which rule applies to a process and the CPUs it would get
+    ///
+    /// Conditions other than the name can only match when `process` carries
+    /// the attribute. Schedules and power targets follow the current time and
+    /// power source.
+    pub fn explain(config_path: &str, process: &ProcessView) -> Result<String, ServiceError> {
         let config = ConfigLoader::load(config_path)?;
-        let config = Arc::new(config);
+        let core_info = Self::detect(&config)?;
+
+        let mut rules = RuleSet::from_config(&config)?;
+        rules.check_targets(&core_info)?;
+        rules.update_schedules(SystemClock.now());
+        if rules.has_power_targets() {
+            rules.set_power_state(default_power_source().power_state().unwrap_or_default());
+        }
+        let decision = rules.explain(process);
+        let mut explanation = decision.to_string();
+
+        if let Some(rule) = decision.rule_index().and_then(|index| rules.get(index)) {
+            // With single_numa_node the node also depends on the PID; show PID 0's
+            let mut cpus = core_info.resolve(rule.target_for(rules.power_state()));
+            if rule.single_numa_node {
+                cpus = core_info.confine_to_numa_node(&cpus, 0);
+            }
+
+            if cpus.is_empty() {
+                explanation.push_str("\n  CPUs on this machine: none, the process would not be pinned");
+            } else {
+                explanation.push_str(&format!("\n  CPUs on this machine: {}", cpus));
+            }
+        }
 
+        Ok(explanation)
+    }
+
+    /// Load the configuration and start logging
+    fn load(config_path: &str) -> Result<Config, ServiceError> {
+        let config = ConfigLoader::load(config_path)?;
+        Self::start(&config, config_path);
+        Ok(config)
+    }
+
+    fn start(config: &Config, origin: &str) {
         // Initialize logger
         crate::utils::logger::init_logger(&config.service.log_level);
 
         log::info!("=== Process CPU Auto Service Starting ===");
-        log::info!("Configuration loaded from: {}", config_path);
+        log::info!("Configuration loaded from: {}", origin);
+    }
 
-        // Detect CPU cores
-        let detection_mode = DetectionMode::from_str(&config.cpu.detection_mode);
+    fn detect(config: &Config) -> Result<Arc<CoreInfo>, ServiceError> {
         let core_info = CpuDetector::detect(
-            detection_mode,
+            config.cpu.detection_mode.clone(),
             config.cpu.p_cores.clone(),
             config.cpu.e_cores.clone(),
         )?;
 
         log::info!("CPU Detection: {}", core_info);
+        Ok(core_info)
+    }
+
+    fn build(
+        config: Config,
+        core_info: Arc<CoreInfo>,
+        source: Box<dyn ProcessSource>,
+        backend: Box<dyn AffinityBackend>,
+        clock: Box<dyn Clock>,
+        power: Box<dyn PowerSource>,
+        focus: Box<dyn FocusProvider>,
+    ) -> Result<Self, ServiceError> {
+        let config = Arc::new(config);
 
         // Create affinity manager
-        let affinity_manager = Arc::new(AffinityManager::new(core_info));
+        let affinity_manager = Arc::new(AffinityManager::with_backend(Arc::clone(&core_info), backend));
 
-        // Create process manager
-        let process_manager = ProcessManager::new(Arc::clone(&config), affinity_manager);
+        // Create process manager; fails on targets that select no processors
+        let process_manager =
+            ProcessManager::with_source(Arc::clone(&config), affinity_manager, source)?
+                .with_clock(clock)
+                .with_power_source(power)
+                .with_focus_provider(focus);
+        if let Some(warning) = process_manager.focus_warning() {
+            log::warn!("{}", warning);
+        }
+
+        for rule in process_manager.rules().rules() {
+            for (field, target) in rule.targets() {
+                log::info!("Rule {}: {} {} is CPUs {}", rule.name, field, target, core_info.resolve(target));
+            }
+            if let Some(idle) = &rule.idle_demotion {
+                log::info!("Rule {}: idle processes move to {}", rule.name, idle);
+            }
+        }
 
         Ok(Self {
             config,
             process_manager,
+            replay_frames: None,
         })
     }
 
     pub fn run(&mut self) -> Result<(), ServiceError> {
+        if let Some(frames) = self.replay_frames {
+            return self.run_replay(frames);
+        }
+
         log::info!("Service runner started. Press Ctrl+C to stop.");
         log::info!("Scan interval: {}ms", self.config.service.scan_interval_ms);
         log::info!("Whitelisted processes: {:?}", self.config.whitelist.processes);
@@ -82,6 +249,25 @@ impl ServiceRunner {
         }
     }
 
+    fn run_replay(&mut self, frames: usize) -> Result<(), ServiceError> {
+        let mut total = 0;
+
+        for _ in 0..frames {
+            let count = self.process_manager.scan_and_process()?;
+            if count > 0 {
+                log::info!("Processed {} new processes", count);
+            }
+            total += count;
+
+            // Exited PIDs are forgotten after every frame so reused PIDs replay as new
+            self.process_manager.cleanup_cache();
+        }
+
+        self.replay_frames = Some(0);
+        log::info!("Replay finished: {} snapshots, {} processes pinned", frames, total);
+        Ok(())
+    }
+
     pub fn run_once(&mut self) -> Result<usize, ServiceError> {
         self.process_manager.scan_and_process()
     }
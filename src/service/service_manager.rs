@@ -9,7 +9,7 @@ use windows_service::service_control_handler::{self, ServiceControlHandlerResult
 use windows_service::{define_windows_service, service_dispatcher};
 
 use crate::config::ConfigLoader;
-use crate::cpu::{AffinityManager, CpuDetector, DetectionMode};
+use crate::cpu::{AffinityManager, CpuDetector};
 use crate::process::ProcessManager;
 use crate::utils::ServiceError;
 
@@ -39,6 +39,12 @@ impl ServiceControl {
     }
 }
 
+impl Default for ServiceControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Run the Windows service
 pub fn run_service() -> Result<(), ServiceError> {
     service_dispatcher::start(SERVICE_NAME, ffi_service_main).map_err(|e| {
@@ -102,9 +108,8 @@ fn run_service_logic() -> Result<(), ServiceError> {
     log::info!("Configuration loaded from: {}", config_path);
 
     // Detect CPU cores
-    let detection_mode = DetectionMode::from_str(&config.cpu.detection_mode);
     let core_info = CpuDetector::detect(
-        detection_mode,
+        config.cpu.detection_mode.clone(),
         config.cpu.p_cores.clone(),
         config.cpu.e_cores.clone(),
     )?;
@@ -115,7 +120,10 @@ fn run_service_logic() -> Result<(), ServiceError> {
     let affinity_manager = Arc::new(AffinityManager::new(core_info));
 
     // Create process manager
-    let mut process_manager = ProcessManager::new(Arc::clone(&config), affinity_manager);
+    let mut process_manager = ProcessManager::new(Arc::clone(&config), affinity_manager)?;
+    if let Some(warning) = process_manager.focus_warning() {
+        log::warn!("{}", warning);
+    }
 
     // Tell Windows we're running
     status_handle
@@ -0,0 +1,5 @@
+pub mod clock;
+pub mod system;
+
+pub use clock::SimulatedClock;
+pub use system::SimulatedSystem;
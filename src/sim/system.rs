@@ -0,0 +1,330 @@
+use crate::cpu::{AffinityBackend, CpuSet};
+use crate::focus::FocusProvider;
+use crate::power::{PowerSource, PowerState};
+use crate::process::{ProcessInfo, ProcessOwner, ProcessSource};
+use crate::utils::ServiceError;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+struct SimProcess {
+    info: ProcessInfo,
+    exe_path: Option<PathBuf>,
+    cmdline: Option<String>,
+    owner: Option<ProcessOwner>,
+    session: Option<u32>,
+    cpu_time: Duration,
+    /// Spawn order, as milliseconds
+    start_time: Duration,
+    affinity: CpuSet,
+    access_denied: bool,
+    pending_failures: u32,
+}
+
+#[derive(Debug, Default)]
+struct SimState {
+    processes: BTreeMap<u32, SimProcess>,
+    default_affinity: CpuSet,
+    snapshot_count: usize,
+    spawn_count: u64,
+    exe_path_queries: usize,
+    cmdline_queries: usize,
+    set_attempts: Vec<(u32, CpuSet)>,
+    power_state: Option<PowerState>,
+    power_queries: usize,
+    foreground_pid: Option<u32>,
+}
+
+/// An in-memory process table and affinity store
+///
+/// Clones share the same state, so one handle can be given to
+/// `ProcessManager` as its `ProcessSource`, `PowerSource` and `FocusProvider`
+/// and another to `AffinityManager` as its `AffinityBackend`, while the test
+/// keeps a third to script processes and inspect the results.
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedSystem {
+    state: Arc<Mutex<SimState>>,
+}
+
+impl SimulatedSystem {
+    /// Create an empty system with the given number of logical processors
+    pub fn new(cpu_count: u32) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SimState {
+                default_affinity: CpuSet::from_cpus(0..cpu_count),
+                ..Default::default()
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, SimState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Start a process; it inherits the default affinity of the system and
+    /// starts after every process spawned before it
+    pub fn spawn(&self, pid: u32, name: &str, parent_pid: u32) {
+        let mut state = self.state();
+        let affinity = state.default_affinity.clone();
+        state.spawn_count += 1;
+        let start_time = Duration::from_millis(state.spawn_count);
+        state.processes.insert(
+            pid,
+            SimProcess {
+                info: ProcessInfo {
+                    pid,
+                    name: name.to_string(),
+                    parent_pid,
+                },
+                exe_path: None,
+                cmdline: None,
+                owner: None,
+                session: None,
+                cpu_time: Duration::ZERO,
+                start_time,
+                affinity,
+                access_denied: false,
+                pending_failures: 0,
+            },
+        );
+    }
+
+    /// Terminate a process
+    pub fn exit(&self, pid: u32) {
+        self.state().processes.remove(&pid);
+    }
+
+    /// Give a process a full executable path
+    pub fn set_exe_path(&self, pid: u32, path: &str) {
+        if let Some(process) = self.state().processes.get_mut(&pid) {
+            process.exe_path = Some(PathBuf::from(path));
+        }
+    }
+
+    /// Give a process a command line
+    pub fn set_cmdline(&self, pid: u32, cmdline: &str) {
+        if let Some(process) = self.state().processes.get_mut(&pid) {
+            process.cmdline = Some(cmdline.to_string());
+        }
+    }
+
+    /// Run a process as a named user in a login session
+    pub fn set_owner(&self, pid: u32, user: &str, session: u32) {
+        if let Some(process) = self.state().processes.get_mut(&pid) {
+            process.owner = Some(ProcessOwner {
+                name: Some(user.to_string()),
+                uid: None,
+            });
+            process.session = Some(session);
+        }
+    }
+
+    /// Charge a process for CPU time it has used
+    pub fn add_cpu_time(&self, pid: u32, used: Duration) {
+        if let Some(process) = self.state().processes.get_mut(&pid) {
+            process.cpu_time += used;
+        }
+    }
+
+    /// Make every affinity call for the process fail with access denied
+    pub fn deny_access(&self, pid: u32) {
+        if let Some(process) = self.state().processes.get_mut(&pid) {
+            process.access_denied = true;
+        }
+    }
+
+    /// Make the next `count` affinity changes for the process fail with a
+    /// transient error
+    pub fn fail_next_set(&self, pid: u32, count: u32) {
+        if let Some(process) = self.state().processes.get_mut(&pid) {
+            process.pending_failures = count;
+        }
+    }
+
+    /// Switch between AC and battery power; unknown until set
+    pub fn set_power_state(&self, power_state: PowerState) {
+        self.state().power_state = Some(power_state);
+    }
+
+    /// Bring a process to the foreground, or `None` for no foreground window
+    pub fn set_foreground(&self, pid: Option<u32>) {
+        self.state().foreground_pid = pid;
+    }
+
+    /// Current affinity of a process, if it is running
+    pub fn affinity_of(&self, pid: u32) -> Option<CpuSet> {
+        self.state().processes.get(&pid).map(|p| p.affinity.clone())
+    }
+
+    /// Every attempt to set affinity, including failed ones, in order
+    pub fn set_attempts(&self) -> Vec<(u32, CpuSet)> {
+        self.state().set_attempts.clone()
+    }
+
+    /// Number of snapshots taken so far
+    pub fn snapshot_count(&self) -> usize {
+        self.state().snapshot_count
+    }
+
+    /// Number of executable path lookups made so far
+    pub fn exe_path_queries(&self) -> usize {
+        self.state().exe_path_queries
+    }
+
+    /// Number of power source queries made so far
+    pub fn power_queries(&self) -> usize {
+        self.state().power_queries
+    }
+
+    /// Number of command line lookups made so far
+    pub fn cmdline_queries(&self) -> usize {
+        self.state().cmdline_queries
+    }
+}
+
+impl ProcessSource for SimulatedSystem {
+    fn snapshot(&mut self) -> Result<Vec<ProcessInfo>, ServiceError> {
+        let mut state = self.state();
+        state.snapshot_count += 1;
+        Ok(state.processes.values().map(|p| p.info.clone()).collect())
+    }
+
+    fn exe_path(&self, pid: u32) -> Option<PathBuf> {
+        let mut state = self.state();
+        state.exe_path_queries += 1;
+        state.processes.get(&pid).and_then(|p| p.exe_path.clone())
+    }
+
+    fn cmdline(&self, pid: u32) -> Option<String> {
+        let mut state = self.state();
+        state.cmdline_queries += 1;
+        state.processes.get(&pid).and_then(|p| p.cmdline.clone())
+    }
+
+    fn owner(&self, pid: u32) -> Option<ProcessOwner> {
+        self.state().processes.get(&pid).and_then(|p| p.owner.clone())
+    }
+
+    fn session(&self, pid: u32) -> Option<u32> {
+        self.state().processes.get(&pid).and_then(|p| p.session)
+    }
+
+    fn cpu_time(&self, pid: u32) -> Option<Duration> {
+        self.state().processes.get(&pid).map(|p| p.cpu_time)
+    }
+
+    fn start_time(&self, pid: u32) -> Option<Duration> {
+        self.state().processes.get(&pid).map(|p| p.start_time)
+    }
+}
+
+impl FocusProvider for SimulatedSystem {
+    fn foreground_pid(&self) -> Option<u32> {
+        self.state().foreground_pid
+    }
+}
+
+impl PowerSource for SimulatedSystem {
+    fn power_state(&self) -> Option<PowerState> {
+        let mut state = self.state();
+        state.power_queries += 1;
+        state.power_state
+    }
+}
+
+impl AffinityBackend for SimulatedSystem {
+    fn get_affinity(&self, pid: u32) -> Result<CpuSet, ServiceError> {
+        let state = self.state();
+        match state.processes.get(&pid) {
+            Some(process) if process.access_denied => Err(ServiceError::PermissionDenied(format!(
+                "Access denied to PID: {}",
+                pid
+            ))),
+            Some(process) => Ok(process.affinity.clone()),
+            None => Err(ServiceError::AffinitySetting(format!("No process with PID: {}", pid))),
+        }
+    }
+
+    fn set_affinity(&self, pid: u32, cpus: &CpuSet) -> Result<(), ServiceError> {
+        let mut state = self.state();
+        state.set_attempts.push((pid, cpus.clone()));
+
+        let process = state
+            .processes
+            .get_mut(&pid)
+            .ok_or_else(|| ServiceError::AffinitySetting(format!("No process with PID: {}", pid)))?;
+
+        if process.access_denied {
+            return Err(ServiceError::PermissionDenied(format!("Access denied to PID: {}", pid)));
+        }
+
+        if process.pending_failures > 0 {
+            process.pending_failures -= 1;
+            return Err(ServiceError::TransientAffinity(format!(
+                "Simulated transient failure for PID: {}",
+                pid
+            )));
+        }
+
+        process.affinity = cpus.clone();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spawn_and_exit() {
+        let mut sim = SimulatedSystem::new(8);
+        sim.spawn(10, "a.exe", 1);
+        sim.spawn(11, "b.exe", 10);
+
+        let processes = sim.snapshot().unwrap();
+        assert_eq!(processes.len(), 2);
+        assert_eq!(processes[1].parent_pid, 10);
+        assert_eq!(sim.affinity_of(10), Some(CpuSet::from_cpus(0..8)));
+
+        sim.set_exe_path(11, "C:/Apps/b.exe");
+        assert_eq!(sim.exe_path(11), Some(PathBuf::from("C:/Apps/b.exe")));
+        assert_eq!(sim.exe_path(10), None);
+        assert_eq!(sim.exe_path_queries(), 2);
+
+        sim.set_cmdline(11, "b.exe --flag");
+        assert_eq!(sim.cmdline(11).as_deref(), Some("b.exe --flag"));
+        assert_eq!(sim.cmdline_queries(), 1);
+
+        sim.set_owner(11, "KIOSK-PC\\kiosk", 2);
+        assert_eq!(sim.owner(11).unwrap().user_name(), Some("kiosk"));
+        assert_eq!(sim.session(11), Some(2));
+        assert_eq!(sim.session(10), None);
+
+        sim.exit(10);
+        assert_eq!(sim.snapshot().unwrap().len(), 1);
+        assert_eq!(sim.affinity_of(10), None);
+        assert_eq!(sim.snapshot_count(), 2);
+    }
+
+    #[test]
+    fn test_affinity_failures() {
+        let sim = SimulatedSystem::new(8);
+        sim.spawn(10, "a.exe", 1);
+        sim.spawn(11, "b.exe", 1);
+        sim.deny_access(10);
+        sim.fail_next_set(11, 1);
+        let cpus = CpuSet::from_cpus([0, 1]);
+
+        assert!(matches!(sim.set_affinity(10, &cpus), Err(ServiceError::PermissionDenied(_))));
+        assert!(sim.get_affinity(10).is_err());
+
+        assert!(sim.set_affinity(11, &cpus).is_err());
+        assert!(sim.set_affinity(11, &cpus).is_ok());
+        assert_eq!(sim.get_affinity(11).unwrap(), cpus);
+
+        assert!(sim.set_affinity(99, &cpus).is_err());
+        assert_eq!(sim.set_attempts().len(), 4);
+    }
+}
@@ -14,6 +14,11 @@ pub enum ServiceError {
     #[error("Affinity setting error: {0}")]
     AffinitySetting(String),
 
+    /// An affinity change the OS refused for now, such as for lack of
+    /// memory; the only affinity error worth retrying
+    #[error("Transient affinity setting error: {0}")]
+    TransientAffinity(String),
+
     #[error("Windows API error: {0}")]
     WindowsApi(String),
 
@@ -1,6 +1,10 @@
+pub mod clock;
 pub mod error;
 pub mod logger;
 pub mod privilege;
 
+pub use clock::{Clock, SystemClock};
 pub use error::ServiceError;
-pub use privilege::{is_elevated, require_administrator};
+pub use privilege::is_elevated;
+#[cfg(windows)]
+pub use privilege::require_administrator;
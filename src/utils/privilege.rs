@@ -1,10 +1,14 @@
+#[cfg(windows)]
 use windows::Win32::Foundation::{CloseHandle, HANDLE};
+#[cfg(windows)]
 use windows::Win32::Security::{
     GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY,
 };
+#[cfg(windows)]
 use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
 
 /// Check if the current process is running with administrator privileges
+#[cfg(windows)]
 pub fn is_elevated() -> bool {
     unsafe {
         let mut token: HANDLE = HANDLE::default();
@@ -38,7 +42,23 @@ pub fn is_elevated() -> bool {
     }
 }
 
+/// Check if the current process is running as root (effective UID 0)
+#[cfg(not(windows))]
+pub fn is_elevated() -> bool {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            // Uid: real effective saved filesystem
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix("Uid:"))
+                .and_then(|uids| uids.split_whitespace().nth(1).map(|euid| euid == "0"))
+        })
+        .unwrap_or(false)
+}
+
 /// Check administrator privileges and exit with error message if not elevated
+#[cfg(windows)]
 pub fn require_administrator() -> Result<(), crate::utils::ServiceError> {
     if !is_elevated() {
         eprintln!("\n╔════════════════════════════════════════════════════════════════╗");
@@ -0,0 +1,734 @@
+use process_cpu_auto::cpu::NumaNode;
+use process_cpu_auto::focus::NoFocusProvider;
+use process_cpu_auto::process::{RecordingSource, ReplaySource, TraceHeader};
+use chrono::{Duration, TimeZone, Utc};
+use process_cpu_auto::{
+    AffinityManager, Config, CoreInfo, CoreTarget, CpuSet, PowerState, ProcessManager, ProcessSource,
+    SimulatedClock, SimulatedSystem,
+};
+use std::sync::Arc;
+
+fn p_cores() -> CpuSet {
+    CpuSet::from_cpus(0..4)
+}
+
+fn all_cores() -> CpuSet {
+    CpuSet::from_cpus(0..8)
+}
+
+fn test_config() -> Config {
+    let mut config = Config::default();
+    config.whitelist.processes = vec!["game.exe".to_string(), "*.render.exe".to_string()];
+    config.advanced.retry_attempts = 3;
+    config.advanced.retry_delay_ms = 0;
+    config
+}
+
+/// `test_config()` with the `[[rules]]` of `toml`, and its `[whitelist]` if it has one
+fn config_with(toml: &str) -> Config {
+    let table: toml::Table = toml.parse().unwrap();
+    let parsed: Config = table.clone().try_into().unwrap();
+
+    let mut config = test_config();
+    config.rules = parsed.rules;
+    if table.contains_key("whitelist") {
+        config.whitelist = parsed.whitelist;
+    }
+    config
+}
+
+fn create_engine(sim: &SimulatedSystem, config: Config) -> ProcessManager {
+    create_engine_with_cores(sim, config, CoreInfo::new(p_cores(), CpuSet::from_cpus(4..8)))
+}
+
+fn create_engine_with_cores(sim: &SimulatedSystem, config: Config, core_info: CoreInfo) -> ProcessManager {
+    let core_info = Arc::new(core_info);
+    let affinity_manager = Arc::new(AffinityManager::with_backend(core_info, Box::new(sim.clone())));
+    ProcessManager::with_source(Arc::new(config), affinity_manager, Box::new(sim.clone()))
+        .unwrap()
+        .with_power_source(Box::new(sim.clone()))
+        .with_focus_provider(Box::new(sim.clone()))
+}
+
+/// `create_engine`, but enumerating processes through `source`, such as a
+/// trace recorder or a replay
+fn create_engine_over(sim: &SimulatedSystem, config: Config, source: Box<dyn ProcessSource>) -> ProcessManager {
+    let core_info = Arc::new(CoreInfo::new(p_cores(), CpuSet::from_cpus(4..8)));
+    let affinity_manager = Arc::new(AffinityManager::with_backend(core_info, Box::new(sim.clone())));
+    ProcessManager::with_source(Arc::new(config), affinity_manager, source)
+        .unwrap()
+        .with_power_source(Box::new(sim.clone()))
+        .with_focus_provider(Box::new(sim.clone()))
+}
+
+#[test]
+fn pins_whitelisted_processes_only() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "game.exe", 1);
+    sim.spawn(101, "scene.render.exe", 1);
+    sim.spawn(102, "notepad.exe", 1);
+
+    let mut engine = create_engine(&sim, test_config());
+    assert_eq!(engine.scan_and_process().unwrap(), 2);
+
+    assert_eq!(sim.affinity_of(100), Some(p_cores()));
+    assert_eq!(sim.affinity_of(101), Some(p_cores()));
+    assert_eq!(sim.affinity_of(102), Some(all_cores()));
+}
+
+#[test]
+fn whitelist_target_selects_the_tier() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "game.exe", 1);
+
+    let mut config = test_config();
+    config.whitelist.target = CoreTarget::ECores;
+
+    let mut engine = create_engine(&sim, config);
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+    assert_eq!(sim.affinity_of(100), Some(CpuSet::from_cpus(4..8)));
+}
+
+#[test]
+fn rules_pin_background_work_to_e_cores() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "game.exe", 1);
+    sim.spawn(101, "ffmpeg.exe", 1);
+    sim.spawn(102, "backupagent.exe", 1);
+
+    let config = config_with(
+        r#"
+        [[rules]]
+        name = "background"
+        processes = ["ffmpeg.exe", "backup*.exe"]
+        target = "e_cores"
+        "#,
+    );
+
+    let mut engine = create_engine(&sim, config);
+    assert_eq!(engine.scan_and_process().unwrap(), 3);
+
+    // Games still go to P-cores through the whitelist
+    assert_eq!(sim.affinity_of(100), Some(p_cores()));
+    assert_eq!(sim.affinity_of(101), Some(CpuSet::from_cpus(4..8)));
+    assert_eq!(sim.affinity_of(102), Some(CpuSet::from_cpus(4..8)));
+}
+
+#[test]
+fn path_rules_look_up_paths_only_when_names_match() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "doom.exe", 1);
+    sim.spawn(101, "doom.exe", 1);
+    sim.spawn(102, "notepad.exe", 1);
+    sim.set_exe_path(100, r"D:\Games\id\doom.exe");
+    sim.set_exe_path(101, r"C:\Downloads\doom.exe");
+
+    let config = config_with(
+        r#"
+        [[rules]]
+        name = "games"
+        processes = ["doom*.exe"]
+        paths = ['D:\Games\**\*.exe']
+        target = "p_cores"
+        "#,
+    );
+
+    let mut engine = create_engine(&sim, config);
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+
+    assert_eq!(sim.affinity_of(100), Some(p_cores()));
+    assert_eq!(sim.affinity_of(101), Some(all_cores()));
+    assert_eq!(sim.affinity_of(102), Some(all_cores()));
+    assert_eq!(sim.exe_path_queries(), 2);
+}
+
+#[test]
+fn cmdline_rules_tell_jvms_apart() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "java.exe", 1);
+    sim.spawn(101, "java.exe", 1);
+    sim.spawn(102, "notepad.exe", 1);
+    sim.set_cmdline(100, "java -Xmx2g org.gradle.launcher.daemon.bootstrap.GradleDaemon 8.5");
+    sim.set_cmdline(101, "java -jar language-server.jar");
+    sim.set_cmdline(102, "notepad.exe");
+
+    let config = config_with(
+        r#"
+        [[rules]]
+        name = "gradle"
+        processes = ["java.exe"]
+        cmdline = ["*GradleDaemon*"]
+        target = "p_cores"
+        "#,
+    );
+
+    let mut engine = create_engine(&sim, config);
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+
+    assert_eq!(sim.affinity_of(100), Some(p_cores()));
+    assert_eq!(sim.affinity_of(101), Some(all_cores()));
+    // notepad.exe never reaches the cmdline check
+    assert_eq!(sim.cmdline_queries(), 2);
+}
+
+#[test]
+fn children_inherit_rules_down_to_max_depth() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "devenv.exe", 1);
+    sim.spawn(101, "msbuild.exe", 100);
+    sim.spawn(102, "cl.exe", 101);
+    sim.spawn(103, "vctip.exe", 102);
+    sim.spawn(104, "ffmpeg.exe", 100);
+    sim.spawn(105, "svchost.exe", 100);
+    sim.spawn(200, "launcher.exe", 1);
+    sim.spawn(201, "crashpad.exe", 200);
+
+    let config = config_with(
+        r#"
+        [[rules]]
+        name = "ide"
+        processes = ["devenv.exe"]
+        inherit_children = true
+        max_depth = 2
+        target = "p_cores"
+
+        [[rules]]
+        name = "background"
+        processes = ["ffmpeg.exe", "launcher.exe"]
+        target = "e_cores"
+        "#,
+    );
+
+    let mut engine = create_engine(&sim, config);
+    assert_eq!(engine.scan_and_process().unwrap(), 5);
+
+    // Children and grandchildren follow devenv.exe, but not great-grandchildren
+    assert_eq!(sim.affinity_of(101), Some(p_cores()));
+    assert_eq!(sim.affinity_of(102), Some(p_cores()));
+    assert_eq!(sim.affinity_of(103), Some(all_cores()));
+    // A child's own rule and the exclude list still win
+    assert_eq!(sim.affinity_of(104), Some(CpuSet::from_cpus(4..8)));
+    assert_eq!(sim.affinity_of(105), Some(all_cores()));
+    // Rules without inherit_children leave children alone
+    assert_eq!(sim.affinity_of(201), Some(all_cores()));
+
+    // Children started later are picked up on the next scan
+    sim.spawn(106, "link.exe", 101);
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+    assert_eq!(sim.affinity_of(106), Some(p_cores()));
+
+    // A devenv.exe that reuses the PID of an exited parent is not the parent
+    sim.spawn(300, "node.exe", 1);
+    sim.spawn(301, "tsserver.exe", 300);
+    sim.exit(300);
+    sim.spawn(300, "devenv.exe", 1);
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+    assert_eq!(sim.affinity_of(300), Some(p_cores()));
+    assert_eq!(sim.affinity_of(301), Some(all_cores()));
+}
+
+#[test]
+fn user_rules_only_pin_the_kiosk_account() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "chrome.exe", 1);
+    sim.spawn(101, "chrome.exe", 1);
+    sim.spawn(102, "chrome.exe", 1);
+    sim.set_owner(100, r"LAB-07\kiosk", 1);
+    sim.set_owner(101, r"LAB-07\alice", 1);
+    sim.set_owner(102, r"LAB-07\kiosk", 3);
+
+    let config = config_with(
+        r#"
+        [[rules]]
+        name = "kiosk"
+        processes = ["chrome.exe"]
+        users = ["kiosk"]
+        sessions = [1]
+        target = "e_cores"
+        "#,
+    );
+
+    let mut engine = create_engine(&sim, config);
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+
+    assert_eq!(sim.affinity_of(100), Some(CpuSet::from_cpus(4..8)));
+    assert_eq!(sim.affinity_of(101), Some(all_cores()));
+    assert_eq!(sim.affinity_of(102), Some(all_cores()));
+}
+
+#[test]
+fn single_numa_node_keeps_each_process_on_one_socket() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "game.exe", 1);
+    sim.spawn(101, "scene.render.exe", 1);
+
+    let mut config = test_config();
+    config.whitelist.target = CoreTarget::AllCores;
+    config.whitelist.single_numa_node = true;
+
+    let core_info = CoreInfo::all_cores(all_cores()).with_numa_nodes(vec![
+        NumaNode { node: 0, cpus: CpuSet::from_cpus(0..4) },
+        NumaNode { node: 1, cpus: CpuSet::from_cpus(4..8) },
+    ]);
+    let mut engine = create_engine_with_cores(&sim, config, core_info);
+    assert_eq!(engine.scan_and_process().unwrap(), 2);
+
+    // Both nodes hold half of the target, so the PID picks the node
+    assert_eq!(sim.affinity_of(100), Some(CpuSet::from_cpus(0..4)));
+    assert_eq!(sim.affinity_of(101), Some(CpuSet::from_cpus(4..8)));
+}
+
+#[test]
+fn targets_naming_undetected_core_sets_fail_at_load() {
+    let sim = SimulatedSystem::new(8);
+    let core_info = Arc::new(CoreInfo::all_cores(all_cores()).with_numa_nodes(vec![
+        NumaNode { node: 0, cpus: CpuSet::from_cpus(0..4) },
+        NumaNode { node: 1, cpus: CpuSet::from_cpus(4..8) },
+    ]));
+    let load = |target: &str| {
+        let mut config = test_config();
+        config.whitelist.target = target.parse().unwrap();
+        let affinity_manager = Arc::new(AffinityManager::with_backend(Arc::clone(&core_info), Box::new(sim.clone())));
+        ProcessManager::with_source(Arc::new(config), affinity_manager, Box::new(sim.clone()))
+    };
+
+    assert!(load("numa1_physical").is_ok());
+    let error = load("ccd9").err().unwrap().to_string();
+    assert!(error.contains("ccd9 is not a core set on this machine (detected: numa0, numa1)"), "{}", error);
+
+    // Core sets every machine has can still be empty
+    for target in ["e_cores", "e_cores_physical", "tier3"] {
+        let error = load(target).err().unwrap().to_string();
+        assert!(error.contains(&format!("{} selects no processors on this machine", target)), "{}", error);
+    }
+}
+
+#[test]
+fn excluded_processes_are_left_alone() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "game.exe", 1);
+
+    let mut config = test_config();
+    config.whitelist.exclude_processes = vec!["game.exe".to_string()];
+
+    let mut engine = create_engine(&sim, config);
+    assert_eq!(engine.scan_and_process().unwrap(), 0);
+    assert_eq!(sim.affinity_of(100), Some(all_cores()));
+    assert!(sim.set_attempts().is_empty());
+}
+
+#[test]
+fn schedules_pin_and_revert_as_windows_open_and_close() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "game.exe", 1);
+    sim.spawn(200, "cl.exe", 1);
+
+    let config = config_with(
+        r#"
+        [[rules]]
+        name = "builds"
+        processes = ["cl.exe"]
+        target = "p_cores"
+
+        [[rules.schedule]]
+        days = ["mon-fri"]
+        start = "09:00"
+        end = "18:00"
+        timezone = "utc"
+
+        [[rules]]
+        name = "evening"
+        priority = 1
+        processes = ["game.exe"]
+        target = "e_cores"
+
+        [[rules.schedule]]
+        start = "19:00"
+        end = "02:00"
+        timezone = "utc"
+        "#,
+    );
+
+    // Monday 08:00
+    let clock = SimulatedClock::new(Utc.with_ymd_and_hms(2026, 3, 2, 8, 0, 0).unwrap());
+    let mut engine = create_engine(&sim, config).with_clock(Box::new(clock.clone()));
+    let e_cores = CpuSet::from_cpus(4..8);
+
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+    assert_eq!(sim.affinity_of(100), Some(p_cores()));
+    assert_eq!(sim.affinity_of(200), Some(all_cores()));
+
+    clock.advance(Duration::hours(1));
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+    assert_eq!(sim.affinity_of(200), Some(p_cores()));
+
+    // Nothing changes within a window
+    let attempts = sim.set_attempts().len();
+    engine.scan_and_process().unwrap();
+    assert_eq!(sim.set_attempts().len(), attempts);
+
+    // 18:00: cl.exe gets its original affinity back
+    clock.advance(Duration::hours(9));
+    assert_eq!(engine.scan_and_process().unwrap(), 0);
+    assert_eq!(sim.affinity_of(200), Some(all_cores()));
+
+    // 19:00: the evening rule outranks the whitelist
+    clock.advance(Duration::hours(1));
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+    assert_eq!(sim.affinity_of(100), Some(e_cores));
+    assert_eq!(engine.explain("game.exe").rule_index(), Some(0));
+
+    // Tuesday 02:00: back to the whitelist rather than the original affinity
+    clock.advance(Duration::hours(7));
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+    assert_eq!(sim.affinity_of(100), Some(p_cores()));
+
+    // Tuesday 09:00: cl.exe is pinned again
+    clock.advance(Duration::hours(7));
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+    assert_eq!(sim.affinity_of(200), Some(p_cores()));
+}
+
+#[test]
+fn power_source_flips_move_processes_between_targets() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "game.exe", 1);
+    sim.spawn(200, "ffmpeg.exe", 1);
+
+    let mut config = config_with(
+        r#"
+        [[rules]]
+        name = "encoders"
+        processes = ["ffmpeg.exe"]
+        target = "e_cores"
+        "#,
+    );
+    config.whitelist.battery_target = Some(CoreTarget::ECores);
+
+    let mut engine = create_engine(&sim, config);
+    let e_cores = CpuSet::from_cpus(4..8);
+
+    // An unknown power state counts as AC
+    assert_eq!(engine.scan_and_process().unwrap(), 2);
+    assert_eq!(sim.affinity_of(100), Some(p_cores()));
+
+    sim.set_power_state(PowerState::Battery);
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+    assert_eq!(sim.affinity_of(100), Some(e_cores.clone()));
+    // Rules without power targets are left alone
+    assert_eq!(sim.set_attempts().iter().filter(|(pid, _)| *pid == 200).count(), 1);
+
+    // Processes started on battery get the battery target
+    sim.spawn(101, "b.render.exe", 1);
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+    assert_eq!(sim.affinity_of(101), Some(e_cores.clone()));
+
+    sim.set_power_state(PowerState::Ac);
+    assert_eq!(engine.scan_and_process().unwrap(), 2);
+    assert_eq!(sim.affinity_of(100), Some(p_cores()));
+    assert_eq!(sim.affinity_of(101), Some(p_cores()));
+    assert_eq!(sim.affinity_of(200), Some(e_cores));
+
+    // Without AC or battery targets the power source is never asked
+    let queries = sim.power_queries();
+    let mut engine = create_engine(&sim, config_with(""));
+    engine.scan_and_process().unwrap();
+    assert_eq!(sim.power_queries(), queries);
+}
+
+#[test]
+fn recorded_battery_decisions_replay_unchanged() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("trace.jsonl");
+    let mut config = test_config();
+    config.whitelist.battery_target = Some(CoreTarget::ECores);
+
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "game.exe", 1);
+    let recorder = RecordingSource::create(&path, TraceHeader::default(), Box::new(sim.clone())).unwrap();
+    let power = recorder.power_source(Box::new(sim.clone()));
+    let mut engine = create_engine_over(&sim, config.clone(), Box::new(recorder)).with_power_source(Box::new(power));
+    engine.scan_and_process().unwrap();
+    sim.set_power_state(PowerState::Battery);
+    engine.scan_and_process().unwrap();
+    sim.spawn(101, "b.render.exe", 1);
+    engine.scan_and_process().unwrap();
+    sim.set_power_state(PowerState::Ac);
+    engine.scan_and_process().unwrap();
+    drop(engine);
+
+    let recorded = sim.set_attempts();
+    assert!(recorded.contains(&(101, CpuSet::from_cpus(4..8))));
+
+    // The replaying machine stays on AC; only the trace knows about the battery
+    let replay_sim = SimulatedSystem::new(8);
+    replay_sim.spawn(100, "game.exe", 1);
+    replay_sim.spawn(101, "b.render.exe", 1);
+    replay_sim.set_power_state(PowerState::Ac);
+    let replay = ReplaySource::open(&path).unwrap();
+    let power = replay.power_source();
+    let mut engine = create_engine_over(&replay_sim, config, Box::new(replay)).with_power_source(Box::new(power));
+    for _ in 0..4 {
+        engine.scan_and_process().unwrap();
+    }
+    assert_eq!(replay_sim.set_attempts(), recorded);
+}
+
+#[test]
+fn recorded_focus_changes_replay_unchanged() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("trace.jsonl");
+    let config = config_with(
+        r#"
+        [[rules]]
+        name = "apps"
+        processes = ["chrome.exe", "code.exe"]
+        target = "e_cores"
+        boost_on_focus = true
+        "#,
+    );
+
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "chrome.exe", 1);
+    sim.spawn(101, "code.exe", 1);
+    let recorder = RecordingSource::create(&path, TraceHeader::default(), Box::new(sim.clone())).unwrap();
+    let focus = recorder.focus_provider(Box::new(sim.clone()));
+    let mut engine = create_engine_over(&sim, config.clone(), Box::new(recorder)).with_focus_provider(Box::new(focus));
+    sim.set_foreground(Some(100));
+    engine.scan_and_process().unwrap();
+    sim.set_foreground(Some(101));
+    engine.scan_and_process().unwrap();
+    drop(engine);
+
+    let recorded = sim.set_attempts();
+    assert!(recorded.contains(&(100, CpuSet::from_cpus(4..8))));
+    assert!(recorded.contains(&(101, p_cores())));
+
+    // Nothing has the focus on the replaying machine; only the trace knows
+    let replay_sim = SimulatedSystem::new(8);
+    replay_sim.spawn(100, "chrome.exe", 1);
+    replay_sim.spawn(101, "code.exe", 1);
+    let replay = ReplaySource::open(&path).unwrap();
+    let focus = replay.focus_provider();
+    let mut engine = create_engine_over(&replay_sim, config, Box::new(replay)).with_focus_provider(Box::new(focus));
+    for _ in 0..2 {
+        engine.scan_and_process().unwrap();
+    }
+    assert_eq!(replay_sim.set_attempts(), recorded);
+}
+
+#[test]
+fn focused_processes_are_boosted_until_focus_moves() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "chrome.exe", 1);
+    sim.spawn(101, "code.exe", 1);
+    sim.spawn(102, "game.exe", 1);
+
+    let config = config_with(
+        r#"
+        [[rules]]
+        name = "apps"
+        processes = ["chrome.exe", "code.exe"]
+        target = "e_cores"
+        boost_on_focus = true
+        "#,
+    );
+
+    let mut engine = create_engine(&sim, config);
+    let e_cores = CpuSet::from_cpus(4..8);
+
+    // Focus on a process that is not pinned yet boosts it once it is
+    sim.set_foreground(Some(100));
+    assert_eq!(engine.scan_and_process().unwrap(), 3);
+    assert_eq!(sim.affinity_of(100), Some(p_cores()));
+    assert_eq!(sim.affinity_of(101), Some(e_cores.clone()));
+
+    // Nothing is set again while focus stays
+    let attempts = sim.set_attempts().len();
+    engine.scan_and_process().unwrap();
+    assert_eq!(sim.set_attempts().len(), attempts);
+
+    sim.set_foreground(Some(101));
+    engine.scan_and_process().unwrap();
+    assert_eq!(sim.affinity_of(100), Some(e_cores.clone()));
+    assert_eq!(sim.affinity_of(101), Some(p_cores()));
+
+    // Rules without boost_on_focus are left alone
+    sim.set_foreground(Some(102));
+    engine.scan_and_process().unwrap();
+    assert_eq!(sim.affinity_of(101), Some(e_cores.clone()));
+    assert_eq!(sim.set_attempts().iter().filter(|(pid, _)| *pid == 102).count(), 1);
+
+    // A boosted process that exits is simply forgotten
+    sim.set_foreground(Some(100));
+    engine.scan_and_process().unwrap();
+    sim.exit(100);
+    sim.set_foreground(None);
+    let attempts = sim.set_attempts().len();
+    engine.scan_and_process().unwrap();
+    assert_eq!(sim.set_attempts().len(), attempts);
+
+    // A provider that can never see focus is reported at startup
+    assert_eq!(engine.focus_warning(), None);
+    let engine = engine.with_focus_provider(Box::new(NoFocusProvider));
+    assert_eq!(
+        engine.focus_warning().as_deref(),
+        Some("boost_on_focus of rule apps will never boost anything: this platform has no focus provider")
+    );
+}
+
+#[test]
+fn idle_processes_are_demoted_and_promoted_with_hysteresis() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "chrome.exe", 1);
+    sim.spawn(101, "chrome.exe", 100);
+
+    let config = config_with(
+        r#"
+        [whitelist]
+        processes = ["chrome.exe"]
+        [whitelist.idle_demotion]
+        demote_below_percent = 2.0
+        promote_above_percent = 10.0
+        window_secs = 10
+        "#,
+    );
+
+    let clock = SimulatedClock::new(Utc.with_ymd_and_hms(2026, 3, 2, 12, 0, 0).unwrap());
+    let mut engine = create_engine(&sim, config).with_clock(Box::new(clock.clone()));
+    let e_cores = CpuSet::from_cpus(4..8);
+    assert_eq!(engine.scan_and_process().unwrap(), 2);
+
+    // One scan every 5 seconds, charging each process some CPU time first
+    let mut scan = |busy_ms: u64, idle_ms: u64| {
+        clock.advance(Duration::seconds(5));
+        sim.add_cpu_time(100, std::time::Duration::from_millis(busy_ms));
+        sim.add_cpu_time(101, std::time::Duration::from_millis(idle_ms));
+        engine.scan_and_process().unwrap();
+    };
+
+    // Nothing moves until a full window has been sampled
+    scan(2500, 50);
+    assert_eq!(sim.affinity_of(101), Some(p_cores()));
+    scan(2500, 50);
+    assert_eq!(sim.affinity_of(101), Some(e_cores.clone()));
+    assert_eq!(sim.affinity_of(100), Some(p_cores()));
+
+    // Between the thresholds a demoted process stays demoted
+    scan(2500, 250);
+    scan(2500, 250);
+    assert_eq!(sim.affinity_of(101), Some(e_cores));
+
+    scan(2500, 1000);
+    assert_eq!(sim.affinity_of(101), Some(p_cores()));
+    assert_eq!(sim.set_attempts().iter().filter(|(pid, _)| *pid == 100).count(), 1);
+    assert_eq!(sim.set_attempts().iter().filter(|(pid, _)| *pid == 101).count(), 3);
+}
+
+#[test]
+fn processes_gaining_focus_are_not_demoted_first() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "chrome.exe", 1);
+
+    let config = config_with(
+        r#"
+        [whitelist]
+        processes = ["chrome.exe"]
+        boost_on_focus = true
+        [whitelist.idle_demotion]
+        demote_below_percent = 2.0
+        promote_above_percent = 10.0
+        window_secs = 10
+        "#,
+    );
+
+    let clock = SimulatedClock::new(Utc.with_ymd_and_hms(2026, 3, 2, 12, 0, 0).unwrap());
+    let mut engine = create_engine(&sim, config).with_clock(Box::new(clock.clone()));
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+    clock.advance(Duration::seconds(5));
+    engine.scan_and_process().unwrap();
+
+    // Idle over a full window by the scan in which it gets focus: boosted,
+    // without a detour through the idle target
+    clock.advance(Duration::seconds(5));
+    sim.set_foreground(Some(100));
+    engine.scan_and_process().unwrap();
+    assert_eq!(sim.affinity_of(100), Some(p_cores()));
+    assert_eq!(sim.set_attempts().len(), 2);
+
+    // Sampling starts over once focus moves away
+    sim.set_foreground(None);
+    for _ in 0..2 {
+        clock.advance(Duration::seconds(5));
+        engine.scan_and_process().unwrap();
+        assert_eq!(sim.affinity_of(100), Some(p_cores()));
+    }
+    clock.advance(Duration::seconds(5));
+    engine.scan_and_process().unwrap();
+    assert_eq!(sim.affinity_of(100), Some(CpuSet::from_cpus(4..8)));
+}
+
+#[test]
+fn processes_are_pinned_once_and_new_ones_are_picked_up() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "game.exe", 1);
+
+    let mut engine = create_engine(&sim, test_config());
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+    assert_eq!(engine.scan_and_process().unwrap(), 0);
+    assert_eq!(sim.set_attempts().len(), 1);
+
+    sim.spawn(200, "level.render.exe", 100);
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+    assert_eq!(sim.affinity_of(200), Some(p_cores()));
+    assert_eq!(sim.set_attempts().len(), 2);
+    assert_eq!(sim.snapshot_count(), 3);
+}
+
+#[test]
+fn transient_failures_are_retried() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "game.exe", 1);
+    sim.fail_next_set(100, 2);
+
+    let mut engine = create_engine(&sim, test_config());
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+    assert_eq!(sim.affinity_of(100), Some(p_cores()));
+    assert_eq!(sim.set_attempts().len(), 3);
+}
+
+#[test]
+fn access_denied_is_not_retried() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "game.exe", 1);
+    sim.deny_access(100);
+
+    let mut engine = create_engine(&sim, test_config());
+    assert_eq!(engine.scan_and_process().unwrap(), 0);
+    assert_eq!(sim.set_attempts().len(), 1);
+
+    // Failed processes are not retried on later scans
+    assert_eq!(engine.scan_and_process().unwrap(), 0);
+    assert_eq!(sim.set_attempts().len(), 1);
+    assert_eq!(sim.affinity_of(100), Some(all_cores()));
+}
+
+#[test]
+fn cleanup_forgets_exited_processes() {
+    let sim = SimulatedSystem::new(8);
+    sim.spawn(100, "game.exe", 1);
+    sim.spawn(101, "notepad.exe", 1);
+
+    let mut engine = create_engine(&sim, test_config());
+    engine.scan_and_process().unwrap();
+
+    sim.exit(100);
+    engine.scan_and_process().unwrap();
+    assert_eq!(engine.cleanup_cache(), 1);
+    assert_eq!(engine.cleanup_cache(), 0);
+
+    // A new process reusing the PID is treated as new
+    sim.spawn(100, "game.exe", 1);
+    assert_eq!(engine.scan_and_process().unwrap(), 1);
+    assert_eq!(sim.set_attempts().len(), 2);
+}